@@ -0,0 +1,171 @@
+// The spans shared by the conformance tests of the codec crates.
+//
+// Include it in the tests with `include!("../../testdata/corpus.rs")`,
+// the golden payloads of each span are saved as `testdata/<format>/<name>.<ext>`.
+//
+// The `thrift` payloads were written by hand, field by field, from `zipkinCore.thrift`
+// and the `TBinaryProtocol` rules. They were NOT captured from Zipkin's Java
+// `SpanBytesEncoder.THRIFT`, because this tree has no JVM or Maven access.
+// To check them against Zipkin 2.x (`io.zipkin.zipkin2:zipkin`), decode each v1 JSON golden
+// and encode it again with Zipkin, in `jshell --class-path zipkin-<version>.jar`:
+//
+//     import zipkin2.codec.*;
+//     var json = java.nio.file.Files.readAllBytes(java.nio.file.Path.of("testdata/v1/root.json"));
+//     var span = SpanBytesDecoder.JSON_V1.decodeOne(json);
+//     java.nio.file.Files.write(java.nio.file.Path.of("root.thrift"),
+//                               SpanBytesEncoder.THRIFT.encode(span));
+//
+// Then compare the output with `testdata/thrift/root.thrift`. Record the Zipkin version
+// here when the goldens are replaced.
+//
+// The `v1` and `v2` JSON payloads were written from the output of the zipkin-json encoder
+// and reviewed by hand, they were NOT captured from Zipkin either. The conformance tests
+// validate them with the JSON schemas in `testdata/schema`, which were transcribed by hand
+// from the `definitions` of `zipkin-api.yaml` and `zipkin2-api.yaml` in openzipkin/zipkin-api,
+// also without network access. The v1 schema allows a string, bool or number `value` with
+// an optional `type` for the binary annotations, as the v1 JSON read by Zipkin, which is
+// the least certain part of the transcription. Diff the schemas against the upstream specs
+// before relying on them. To check the JSON goldens against Zipkin,
+// decode and encode them again in the same `jshell` session:
+//
+//     var v1 = SpanBytesEncoder.JSON_V1.encode(span);
+//     var v2 = SpanBytesEncoder.JSON_V2.encode(span);
+//
+// Then compare the parsed output with `testdata/v1/root.json` and `testdata/v2/root.json`,
+// the key order and the white spaces of the payloads differ.
+
+mod corpus {
+    use std::sync::Arc;
+
+    use zipkin_core::*;
+
+    const TIMESTAMP: i64 = 1472470996;
+
+    fn at<'a>(span: &mut Span<'a>, value: &'a str, us: i32) {
+        span.annotate(value, None);
+        span.annotations.last_mut().unwrap().timestamp = timestamp(TIMESTAMP, us * 1000);
+    }
+
+    fn frontend() -> Arc<Endpoint<'static>> {
+        Arc::new(Endpoint {
+                     name: Some("frontend".into()),
+                     addr: Some("172.17.0.13:8080".parse().unwrap()),
+                 })
+    }
+
+    /// The server span with a 128-bit trace id, called by an IPv6 client.
+    fn root() -> Span<'static> {
+        let mut span = Span::new("get /api")
+            .with_trace_id(TraceId {
+                               lo: 0x48485a3953bb6124,
+                               hi: Some(0x463ac35c9f6413ad),
+                           })
+            .with_id(SpanId(0x48485a3953bb6124))
+            .with_local_endpoint(frontend());
+
+        span.timestamp = timestamp(TIMESTAMP, 0);
+        span.duration = Some(Duration::microseconds(207000));
+        span.set_remote_endpoint(Kind::Server,
+                                 Endpoint {
+                                     name: None,
+                                     addr: Some("[2001:db8::c001]:53210".parse().unwrap()),
+                                 });
+
+        at(&mut span, SERVER_RECV, 0);
+        at(&mut span, SERVER_SEND, 207000);
+
+        span.binary_annotate(HTTP_METHOD, "GET", None);
+        span.binary_annotate(HTTP_PATH, "/api", None);
+
+        span
+    }
+
+    /// The client span of the root, calling an IPv4 backend.
+    fn child() -> Span<'static> {
+        let root = root();
+        let mut span = Span::new("get")
+            .with_trace_id(root.trace_id.clone())
+            .with_id(SpanId(0x5b4185666d50f68b))
+            .with_parent_id(root.id)
+            .with_local_endpoint(frontend());
+
+        span.timestamp = timestamp(TIMESTAMP, 1000000);
+        span.duration = Some(Duration::microseconds(1234));
+        span.set_remote_endpoint(Kind::Client,
+                                 Endpoint {
+                                     name: Some("backend".into()),
+                                     addr: Some("192.168.99.101:9000".parse().unwrap()),
+                                 });
+
+        at(&mut span, CLIENT_SEND, 1000);
+        at(&mut span, WIRE_SEND, 1010);
+        at(&mut span, CLIENT_RECV, 2234);
+
+        span.binary_annotate("clnt/finagle.version", "6.45.0", None);
+
+        span
+    }
+
+    /// The local span with a 64-bit trace id, unicode names and an IPv6 endpoint without port.
+    fn local() -> Span<'static> {
+        let mut span = Span::new("处理 ünïcødé 🚀")
+            .with_trace_id(TraceId {
+                               lo: 0x7a6b5c4d3e2f1a0b,
+                               hi: None,
+                           })
+            .with_id(SpanId(0x7a6b5c4d3e2f1a0b))
+            .with_local_endpoint(Arc::new(Endpoint {
+                                              name: Some("wörker".into()),
+                                              addr: Some("[::1]:0".parse().unwrap()),
+                                          }))
+            .with_debug(true);
+
+        span.timestamp = timestamp(TIMESTAMP, 500000);
+        span.duration = Some(Duration::microseconds(1));
+
+        at(&mut span, "größe → ∞", 500);
+
+        span.binary_annotate(LOCAL_COMPONENT, "scheduler", None);
+
+        span
+    }
+
+    /// The producer span with every kind of the binary annotation values.
+    fn values() -> Span<'static> {
+        let mut span = Span::new("send")
+            .with_trace_id(TraceId {
+                               lo: 0x0000000000000001,
+                               hi: Some(0x0000000000000001),
+                           })
+            .with_id(SpanId(0x0000000000000002))
+            .with_parent_id(SpanId(0xffffffffffffffff))
+            .with_local_endpoint(frontend());
+
+        span.timestamp = timestamp(TIMESTAMP, 0);
+        span.set_remote_endpoint(Kind::Producer,
+                                 Endpoint {
+                                     name: Some("kafka".into()),
+                                     addr: None,
+                                 });
+
+        at(&mut span, MESSAGE_SEND, 0);
+
+        span.binary_annotate("bool.true", true, None);
+        span.binary_annotate("bool.false", false, None);
+        span.binary_annotate("bytes", &b"\x00\x01\xfe\xff"[..], None);
+        span.binary_annotate("blob", b"zipkin".to_vec(), None);
+        span.binary_annotate("i16", -32768i16, None);
+        span.binary_annotate("i32", 2147483647i32, None);
+        span.binary_annotate("i64", -9007199254740993i64, None);
+        span.binary_annotate("double", -123.456, None);
+        span.binary_annotate("str", "\"quoted\"\n\ttabbed\\", None);
+        span.binary_annotate("string", "ünïcødé 🚀".to_owned(), None);
+
+        span
+    }
+
+    /// The name and the span of each case in the corpus
+    pub fn spans() -> Vec<(&'static str, Span<'static>)> {
+        vec![("root", root()), ("child", child()), ("local", local()), ("values", values())]
+    }
+}
@@ -10,6 +10,7 @@ use bytes::BytesMut;
 use zipkin_core::{Codec, Span, Transport, Collector};
 
 use errors::Error;
+use transport::AsyncTransport;
 
 pub trait AsyncCollector: Send {
     type Item;
@@ -31,6 +32,12 @@ fn lock<T, F, E>(m: &Mutex<T>, callback: F) -> Result<(), E>
     }
 }
 
+fn exported<'a>(mut spans: Vec<Span<'a>>) -> Option<Vec<Span<'a>>> {
+    spans.retain(|span| span.decision().is_exported());
+
+    if spans.is_empty() { None } else { Some(spans) }
+}
+
 #[derive(Clone)]
 pub struct BaseAsyncCollector<C, T, E> {
     pub max_message_size: usize,
@@ -59,6 +66,11 @@ impl<'a, C, T, E> Collector for BaseAsyncCollector<C, T, E>
     type Error = E;
 
     fn submit(&self, spans: Self::Item) -> Result<Self::Output, Self::Error> {
+        let spans = match exported(spans) {
+            Some(spans) => spans,
+            None => return Ok(()),
+        };
+
         let mut buf = BytesMut::with_capacity(self.max_message_size);
 
         self.encode(spans, &mut buf)?;
@@ -80,6 +92,11 @@ impl<'a, C, T, E> AsyncCollector for BaseAsyncCollector<C, T, E>
     type Future = BoxFuture<Self::Output, Self::Error>;
 
     fn async_submit(&self, spans: Self::Item) -> Self::Future {
+        let spans = match exported(spans) {
+            Some(spans) => spans,
+            None => return future::ok(()).boxed(),
+        };
+
         let mut buf = BytesMut::with_capacity(self.max_message_size);
 
         if let Err(err) = self.encode(spans, &mut buf) {
@@ -94,19 +111,86 @@ impl<'a, C, T, E> AsyncCollector for BaseAsyncCollector<C, T, E>
     }
 }
 
+/// Submit the spans to an `AsyncTransport`, without waiting for the previous batches.
+#[derive(Clone)]
+pub struct PipelinedCollector<C, T, E> {
+    pub max_message_size: usize,
+    pub encoder: Arc<Mutex<C>>,
+    pub transport: Arc<T>,
+    phantom: PhantomData<fn() -> E>,
+}
+
+impl<C, T, E> PipelinedCollector<C, T, E> {
+    pub fn new(encoder: C, transport: T) -> Self {
+        PipelinedCollector {
+            max_message_size: 4096,
+            encoder: Arc::new(Mutex::new(encoder)),
+            transport: Arc::new(transport),
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn with_max_message_size(self, max_message_size: usize) -> Self {
+        PipelinedCollector { max_message_size: max_message_size, ..self }
+    }
+}
+
+impl<'a, C, T, E> AsyncCollector for PipelinedCollector<C, T, E>
+    where C: Codec<Item = Vec<Span<'a>>, Error = E>,
+          T: AsyncTransport<Buffer = BytesMut, Output = (), Error = E>,
+          T::Future: 'static + Send,
+          E: 'static + From<::std::io::Error> + From<Error> + Send
+{
+    type Item = Vec<Span<'a>>;
+    type Output = ();
+    type Error = E;
+    type Future = BoxFuture<Self::Output, Self::Error>;
+
+    fn async_submit(&self, spans: Self::Item) -> Self::Future {
+        let spans = match exported(spans) {
+            Some(spans) => spans,
+            None => return future::ok(()).boxed(),
+        };
+
+        let mut buf = BytesMut::with_capacity(self.max_message_size);
+
+        if let Err(err) = lock(&self.encoder, |mut encoder| encoder.encode(spans, &mut buf)) {
+            return future::err(err).boxed();
+        }
+
+        self.transport.async_send(buf).boxed()
+    }
+}
+
+impl<'a, C, T, E> Collector for PipelinedCollector<C, T, E>
+    where C: Codec<Item = Vec<Span<'a>>, Error = E>,
+          T: AsyncTransport<Buffer = BytesMut, Output = (), Error = E>,
+          T::Future: 'static + Send,
+          E: 'static + From<::std::io::Error> + From<Error> + Send
+{
+    type Item = Vec<Span<'a>>;
+    type Output = ();
+    type Error = E;
+
+    fn submit(&self, spans: Self::Item) -> Result<Self::Output, Self::Error> {
+        self.async_submit(spans).wait()
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::sync::{Arc, Mutex};
+    use std::sync::{Arc, Mutex, Barrier};
     use std::marker::PhantomData;
 
     use bytes::{BytesMut, BufMut};
 
-    use futures::Future;
-    use futures_cpupool::CpuPool;
+    use futures::{future, Future};
+    use futures_cpupool::{CpuPool, CpuFuture};
 
-    use zipkin_core::{Encoder, Span, Transport};
+    use zipkin_core::{Encoder, Span, Transport, Decision};
 
-    use super::{AsyncCollector, BaseAsyncCollector};
+    use super::{AsyncCollector, BaseAsyncCollector, PipelinedCollector};
+    use super::super::transport::AsyncTransport;
     use super::super::errors::Error;
 
     #[derive(Clone)]
@@ -137,6 +221,34 @@ mod tests {
         }
     }
 
+    struct MockAsyncTransport {
+        sent: Arc<Mutex<Vec<BytesMut>>>,
+        barrier: Arc<Barrier>,
+        thread_pool: CpuPool,
+    }
+
+    impl AsyncTransport for MockAsyncTransport {
+        type Buffer = BytesMut;
+        type Output = ();
+        type Error = Error;
+        type Future = CpuFuture<Self::Output, Self::Error>;
+
+        fn async_send(&self, buf: BytesMut) -> Self::Future {
+            let sent = self.sent.clone();
+            let barrier = self.barrier.clone();
+
+            self.thread_pool
+                .spawn_fn(move || {
+                              // every batch must be in flight before any of them is done
+                              barrier.wait();
+
+                              sent.lock().unwrap().push(buf);
+
+                              Ok(())
+                          })
+        }
+    }
+
     #[derive(Clone)]
     struct MockEncoder<'a, T: 'a> {
         encoded: usize,
@@ -183,5 +295,46 @@ mod tests {
         assert_eq!(collector.encoder.lock().unwrap().encoded, 1);
         assert_eq!(collector.transport.lock().unwrap().sent, 1);
         assert_eq!(collector.transport.lock().unwrap().buf, b"hello world");
+
+        collector
+            .async_submit(vec![Span::new("test").with_decision(Decision::Drop)])
+            .wait()
+            .unwrap();
+
+        assert_eq!(collector.encoder.lock().unwrap().encoded, 1);
+        assert_eq!(collector.transport.lock().unwrap().sent, 1);
+    }
+
+    #[test]
+    fn pipelined_submit() {
+        let transport = MockAsyncTransport {
+            sent: Arc::new(Mutex::new(vec![])),
+            barrier: Arc::new(Barrier::new(3)),
+            thread_pool: CpuPool::new(3),
+        };
+        let collector = PipelinedCollector::new(MockEncoder::new(), transport);
+
+        let pending = (0..3)
+            .map(|_| collector.async_submit(vec![Span::new("test")]))
+            .collect::<Vec<_>>();
+
+        future::join_all(pending).wait().unwrap();
+
+        assert_eq!(collector.encoder.lock().unwrap().encoded, 3);
+        assert_eq!(collector.transport.sent.lock().unwrap().len(), 3);
+        assert!(collector
+                    .transport
+                    .sent
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .all(|buf| &buf[..] == b"hello world"));
+
+        collector
+            .async_submit(vec![Span::new("test").with_decision(Decision::Drop)])
+            .wait()
+            .unwrap();
+
+        assert_eq!(collector.encoder.lock().unwrap().encoded, 3);
     }
-}
\ No newline at end of file
+}
@@ -9,6 +9,8 @@ extern crate zipkin_core;
 
 pub mod errors;
 mod collector;
+mod transport;
 
 pub use errors::{Error, ErrorKind, Result};
-pub use collector::{AsyncCollector, BaseAsyncCollector};
\ No newline at end of file
+pub use collector::{AsyncCollector, BaseAsyncCollector, PipelinedCollector};
+pub use transport::AsyncTransport;
\ No newline at end of file
@@ -0,0 +1,12 @@
+use futures::Future;
+
+/// The transport which sends the encoded spans without blocking the caller,
+/// so several batches could be in flight at the same time.
+pub trait AsyncTransport: Send + Sync {
+    type Buffer: AsRef<[u8]>;
+    type Output;
+    type Error;
+    type Future: Future<Item = Self::Output, Error = Self::Error>;
+
+    fn async_send(&self, buf: Self::Buffer) -> Self::Future;
+}
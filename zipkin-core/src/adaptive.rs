@@ -0,0 +1,276 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+use std::sync::Mutex;
+
+use sampler::{Sampler, random};
+use span::Span;
+
+/// The smoothing factor of the exponentially weighted moving average of the span rate
+const SMOOTHING: f64 = 0.5;
+
+/// The default number of the span names measured separately
+const MAX_NAMES: usize = 1024;
+
+#[derive(Debug)]
+struct Stat {
+    /// Spans seen in the current interval
+    seen: usize,
+    /// Smoothed spans per second
+    rate: Option<f64>,
+    /// Current sampling probability
+    probability: f64,
+}
+
+impl Stat {
+    fn new() -> Self {
+        Stat {
+            seen: 0,
+            rate: None,
+            probability: 1.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    ts: Instant,
+    stats: HashMap<String, Stat>,
+    /// The span names beyond the limit, measured together
+    others: Stat,
+}
+
+impl State {
+    fn adjust(&mut self, target: f64, elapsed: Duration) {
+        let secs = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1000_000_000.0;
+
+        if secs <= 0.0 {
+            return;
+        }
+
+        // forget the span names which disappeared in the last interval
+        self.stats.retain(|_, stat| stat.seen > 0);
+
+        for stat in self.stats.values_mut().chain(Some(&mut self.others)) {
+            let rate = stat.seen as f64 / secs;
+
+            stat.rate = Some(stat.rate
+                                 .map_or(rate, |last| last + SMOOTHING * (rate - last)));
+            stat.seen = 0;
+        }
+
+        // share the budget fairly, the rare span names are sampled completely,
+        // and the remaining budget is split among the hot ones.
+        let mut stats = self.stats
+            .values_mut()
+            .chain(Some(&mut self.others))
+            .collect::<Vec<&mut Stat>>();
+
+        stats.sort_by(|lhs, rhs| lhs.rate.partial_cmp(&rhs.rate).unwrap_or(Ordering::Equal));
+
+        let total = stats.len();
+        let mut budget = target;
+
+        for (i, stat) in stats.into_iter().enumerate() {
+            let rate = stat.rate.unwrap_or(0.0);
+            let share = budget / (total - i) as f64;
+
+            if rate <= share {
+                stat.probability = 1.0;
+                budget -= rate;
+            } else {
+                stat.probability = share / rate;
+                budget -= share;
+            }
+        }
+    }
+}
+
+/// Adaptive sampling targeting a global spans per second budget
+///
+/// It measures the span rate per span name, and adjusts the sampling probability of each name
+/// every interval, so the rare span names are still sampled while the hot ones are throttled.
+#[derive(Debug)]
+pub struct Adaptive<'a> {
+    /// Target spans per second
+    pub target: f64,
+    /// Interval to adjust the probabilities
+    pub interval: Duration,
+    /// The maximum number of the span names measured separately,
+    /// the other span names share one probability.
+    pub max_names: usize,
+    state: Mutex<State>,
+    phantom: PhantomData<fn(&Span<'a>)>,
+}
+
+impl<'a> Adaptive<'a> {
+    pub fn new(target: f64, interval: Duration) -> Self {
+        Adaptive {
+            target: target,
+            interval: interval,
+            max_names: MAX_NAMES,
+            state: Mutex::new(State {
+                                  ts: Instant::now(),
+                                  stats: HashMap::new(),
+                                  others: Stat::new(),
+                              }),
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn per_second(target: f64) -> Self {
+        Adaptive::new(target, Duration::from_secs(1))
+    }
+
+    pub fn with_max_names(self, max_names: usize) -> Self {
+        Adaptive { max_names: max_names, ..self }
+    }
+
+    /// The current sampling probabilities of the span names
+    pub fn probabilities(&self) -> HashMap<String, f64> {
+        self.state
+            .lock()
+            .unwrap()
+            .stats
+            .iter()
+            .map(|(name, stat)| (name.clone(), stat.probability))
+            .collect()
+    }
+}
+
+impl<'a> Sampler for Adaptive<'a> {
+    type Item = Span<'a>;
+
+    fn sample(&self, span: &Self::Item) -> bool {
+        let mut state = self.state.lock().unwrap();
+
+        let elapsed = state.ts.elapsed();
+
+        if elapsed >= self.interval {
+            state.adjust(self.target, elapsed);
+            state.ts = Instant::now();
+        }
+
+        if !state.stats.contains_key(&*span.name) && state.stats.len() < self.max_names {
+            state.stats.insert(span.name.clone().into_owned(), Stat::new());
+        }
+
+        let state = &mut *state;
+        let stat = state.stats.get_mut(&*span.name).unwrap_or(&mut state.others);
+
+        stat.seen += 1;
+
+        stat.probability >= 1.0 || random() < stat.probability
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn adjust() {
+        let sampler = Adaptive::per_second(10.0);
+
+        for _ in 0..1000 {
+            assert!(sampler.sample(&Span::new("hot")));
+        }
+        for _ in 0..2 {
+            assert!(sampler.sample(&Span::new("rare")));
+        }
+
+        {
+            let mut state = sampler.state.lock().unwrap();
+
+            state.adjust(10.0, Duration::from_secs(1));
+        }
+
+        let probabilities = sampler.probabilities();
+
+        assert_eq!(probabilities["rare"], 1.0);
+        assert_eq!(probabilities["hot"], 0.008);
+
+        let sampled = (0..1000)
+            .filter(|_| sampler.sample(&Span::new("hot")))
+            .count();
+
+        assert!(sampled < 100);
+        assert!(sampler.sample(&Span::new("rare")));
+
+        {
+            let mut state = sampler.state.lock().unwrap();
+
+            state.adjust(10.0, Duration::from_secs(1));
+
+            // the smoothed rate of the hot span name is (1000 + 1000) / 2
+            assert_eq!(state.stats["hot"].rate, Some(1000.0));
+            assert_eq!(state.stats["rare"].rate, Some(1.5));
+        }
+
+        let probabilities = sampler.probabilities();
+
+        assert_eq!(probabilities["rare"], 1.0);
+        assert_eq!(probabilities["hot"], 0.0085);
+
+        {
+            let mut state = sampler.state.lock().unwrap();
+
+            state.adjust(10.0, Duration::from_secs(1));
+        }
+
+        assert!(sampler.probabilities().is_empty());
+    }
+
+    #[test]
+    fn interval() {
+        let sampler = Adaptive::new(1.0, Duration::from_millis(10));
+
+        for _ in 0..100 {
+            sampler.sample(&Span::new("test"));
+        }
+
+        ::std::thread::sleep(Duration::from_millis(20));
+
+        sampler.sample(&Span::new("test"));
+
+        assert!(sampler.probabilities()["test"] < 1.0);
+    }
+
+    #[test]
+    fn zero_interval() {
+        let sampler = Adaptive::per_second(1.0);
+
+        for _ in 0..10 {
+            sampler.sample(&Span::new("test"));
+        }
+
+        sampler.state.lock().unwrap().adjust(1.0, Duration::from_secs(0));
+
+        assert_eq!(sampler.probabilities()["test"], 1.0);
+    }
+
+    #[test]
+    fn max_names() {
+        let sampler = Adaptive::per_second(10.0).with_max_names(2);
+        let names = (0..100).map(|i| format!("name{}", i)).collect::<Vec<_>>();
+
+        for name in &names {
+            assert!(sampler.sample(&Span::new(name)));
+        }
+
+        assert_eq!(sampler.probabilities().len(), 2);
+
+        {
+            let mut state = sampler.state.lock().unwrap();
+
+            assert_eq!(state.others.seen, 98);
+
+            state.adjust(10.0, Duration::from_secs(1));
+
+            assert_eq!(state.others.probability, 8.0 / 98.0);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use span::{Timestamp, Duration, now};
+
+/// The source of the span and annotation timestamps
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// Epoch time of now
+    fn now(&self) -> Timestamp;
+}
+
+thread_local!(static SYSTEM_CLOCK: Arc<Clock> = Arc::new(SystemClock));
+
+/// The shared wall clock of the spans created without a tracer.
+pub fn system_clock() -> Arc<Clock> {
+    SYSTEM_CLOCK.with(|clock| clock.clone())
+}
+
+/// The wall clock, which may jump when the system time was adjusted.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        now()
+    }
+}
+
+/// Read the wall clock once, and measure the elapsed time with the monotonic clock.
+///
+/// It is created for each trace, so the durations never go backward even if NTP steps the time.
+#[derive(Clone, Copy, Debug)]
+pub struct AnchoredClock {
+    anchor: Timestamp,
+    instant: Instant,
+}
+
+impl AnchoredClock {
+    pub fn new() -> Self {
+        AnchoredClock {
+            anchor: now(),
+            instant: Instant::now(),
+        }
+    }
+}
+
+impl Default for AnchoredClock {
+    fn default() -> Self {
+        AnchoredClock::new()
+    }
+}
+
+impl Clock for AnchoredClock {
+    fn now(&self) -> Timestamp {
+        self.anchor + Duration::from_std(self.instant.elapsed()).unwrap()
+    }
+}
+
+/// The clock which only moves when told, used for the tests.
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<Timestamp>,
+}
+
+impl ManualClock {
+    pub fn new(now: Timestamp) -> Self {
+        ManualClock { now: Mutex::new(now) }
+    }
+
+    pub fn set(&self, now: Timestamp) {
+        *self.now.lock().unwrap() = now;
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap();
+
+        *now = *now + duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Timestamp {
+        *self.now.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread::sleep;
+    use std::time::Duration as StdDuration;
+
+    use super::*;
+    use span::timestamp;
+
+    #[test]
+    fn anchored() {
+        let clock = AnchoredClock::new();
+        let start = clock.now();
+
+        sleep(StdDuration::from_millis(10));
+
+        assert!(clock.now() - start >= Duration::milliseconds(10));
+        assert!((start - now()).num_seconds().abs() < 1);
+    }
+
+    #[test]
+    fn manual() {
+        let clock = ManualClock::new(timestamp(123, 0));
+
+        assert_eq!(clock.now(), timestamp(123, 0));
+
+        clock.advance(Duration::milliseconds(500));
+
+        assert_eq!(clock.now(), timestamp(123, 500_000_000));
+
+        clock.set(timestamp(0, 0));
+
+        assert_eq!(clock.now(), timestamp(0, 0));
+    }
+
+    #[test]
+    fn debug() {
+        let clock: Arc<Clock> = Arc::new(AnchoredClock::new());
+
+        assert_eq!(format!("{:?}", clock), format!("{:?}", clock));
+        assert_eq!(format!("{:?}", system_clock()), "SystemClock");
+    }
+}
@@ -23,8 +23,62 @@ lazy_static! {
         .collect();
 }
 
+/// The version of the Zipkin API and data model
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApiVersion {
+    /// The v1 model with the annotations and binary annotations
+    V1,
+    /// The v2 model with the span kind, endpoints and tags
+    V2,
+}
+
+impl Default for ApiVersion {
+    fn default() -> Self {
+        ApiVersion::V1
+    }
+}
+
+impl ApiVersion {
+    /// The path of the Zipkin HTTP API which accepts the spans
+    pub fn spans_path(&self) -> &'static str {
+        match *self {
+            ApiVersion::V1 => "/api/v1/spans",
+            ApiVersion::V2 => "/api/v2/spans",
+        }
+    }
+}
+
+/// The content coding of the compressed messages, as the HTTP `Content-Encoding` header
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContentEncoding {
+    /// The gzip file format (RFC 1952)
+    Gzip,
+    /// The zlib data format (RFC 1950), which HTTP names `deflate`
+    Deflate,
+}
+
+impl ContentEncoding {
+    /// The name used by the HTTP `Content-Encoding` header
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Deflate => "deflate",
+        }
+    }
+}
+
 pub trait MimeType {
     fn mime_type(&self) -> Mime;
+
+    /// The content coding of the encoded messages, `None` if they are not compressed.
+    fn content_encoding(&self) -> Option<ContentEncoding> {
+        None
+    }
+
+    /// The data model of the encoded spans
+    fn api_version(&self) -> ApiVersion {
+        ApiVersion::V1
+    }
 }
 
 pub trait Codec: Encoder + Send {}
@@ -74,7 +128,13 @@ impl<'a, C, T, E> Collector for BaseCollector<'a, C, T, E>
     type Output = ();
     type Error = E;
 
-    fn submit(&self, spans: Self::Item) -> Result<Self::Output, Self::Error> {
+    fn submit(&self, mut spans: Self::Item) -> Result<Self::Output, Self::Error> {
+        spans.retain(|span| span.decision().is_exported());
+
+        if spans.is_empty() {
+            return Ok(());
+        }
+
         let mut buf = BytesMut::with_capacity(self.max_message_size);
         {
             if let Ok(mut encoder) = self.encoder.lock() {
@@ -113,6 +173,7 @@ mod tests {
     use bytes::{BytesMut, BufMut};
 
     use super::{Encoder, Transport, Collector, BaseCollector, Span};
+    use super::super::sampler::Decision;
     use super::super::errors::Error;
 
     struct MockTransport {
@@ -186,5 +247,13 @@ mod tests {
         assert_eq!(collector.encoder.lock().unwrap().encoded, 1);
         assert_eq!(collector.transport.lock().unwrap().sent, 1);
         assert_eq!(collector.transport.lock().unwrap().buf, b"hello world");
+
+        collector
+            .submit(vec![Span::new("test").with_decision(Decision::Drop),
+                         Span::new("test").with_decision(Decision::RecordOnly)])
+            .unwrap();
+
+        assert_eq!(collector.encoder.lock().unwrap().encoded, 1);
+        assert_eq!(collector.transport.lock().unwrap().sent, 1);
     }
 }
\ No newline at end of file
@@ -0,0 +1,194 @@
+use std::io::{self, Write};
+
+use bytes::BytesMut;
+
+use flate2;
+use flate2::write::{GzEncoder, ZlibEncoder};
+
+use tokio_io::codec::Encoder;
+
+use mime::Mime;
+
+use collector::{ApiVersion, MimeType, ContentEncoding};
+
+/// The compression level, a balance between speed and size
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// Store the messages without compressing them
+    None,
+    /// Optimize for the speed
+    Fast,
+    /// Optimize for the size
+    Best,
+    /// The balance used by gzip
+    Default,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Compression::Default
+    }
+}
+
+impl From<Compression> for flate2::Compression {
+    fn from(level: Compression) -> Self {
+        match level {
+            Compression::None => flate2::Compression::None,
+            Compression::Fast => flate2::Compression::Fast,
+            Compression::Best => flate2::Compression::Best,
+            Compression::Default => flate2::Compression::Default,
+        }
+    }
+}
+
+/// Compress the messages of the wrapped codec, like JSON or Thrift.
+pub struct Compressed<C> {
+    /// The codec to encode the messages before compressing
+    pub codec: C,
+    /// The content coding of the compressed messages
+    pub encoding: ContentEncoding,
+    /// The compression level, a balance between speed and size
+    pub level: Compression,
+    buf: BytesMut,
+}
+
+impl<C> Compressed<C> {
+    pub fn new(codec: C, encoding: ContentEncoding) -> Self {
+        Compressed {
+            codec: codec,
+            encoding: encoding,
+            level: Compression::Default,
+            buf: BytesMut::new(),
+        }
+    }
+
+    pub fn gzip(codec: C) -> Self {
+        Self::new(codec, ContentEncoding::Gzip)
+    }
+
+    pub fn deflate(codec: C) -> Self {
+        Self::new(codec, ContentEncoding::Deflate)
+    }
+
+    pub fn with_level(self, level: Compression) -> Self {
+        Compressed { level: level, ..self }
+    }
+}
+
+/// Append the compressed bytes to the destination buffer, growing it when necessary.
+struct Extend<'a>(&'a mut BytesMut);
+
+impl<'a> Write for Extend<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.extend_from_slice(buf);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<C> Encoder for Compressed<C>
+    where C: Encoder,
+          C::Error: From<io::Error>
+{
+    type Item = C::Item;
+    type Error = C::Error;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        // the wrapped codec gets the same room as the destination buffer
+        self.buf.clear();
+        self.buf.reserve(dst.capacity());
+
+        self.codec.encode(item, &mut self.buf)?;
+
+        match self.encoding {
+            ContentEncoding::Gzip => {
+                let mut encoder = GzEncoder::new(Extend(dst), self.level.into());
+
+                encoder.write_all(&self.buf)?;
+                encoder.finish()?;
+            }
+            ContentEncoding::Deflate => {
+                let mut encoder = ZlibEncoder::new(Extend(dst), self.level.into());
+
+                encoder.write_all(&self.buf)?;
+                encoder.finish()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<C: MimeType> MimeType for Compressed<C> {
+    fn mime_type(&self) -> Mime {
+        self.codec.mime_type()
+    }
+
+    fn content_encoding(&self) -> Option<ContentEncoding> {
+        Some(self.encoding)
+    }
+
+    fn api_version(&self) -> ApiVersion {
+        self.codec.api_version()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::prelude::*;
+
+    use flate2::read::{GzDecoder, ZlibDecoder};
+
+    use super::*;
+
+    struct Plain;
+
+    impl Encoder for Plain {
+        type Item = String;
+        type Error = io::Error;
+
+        fn encode(&mut self, item: Self::Item, dst: &mut BytesMut) -> io::Result<()> {
+            dst.extend_from_slice(item.as_bytes());
+
+            Ok(())
+        }
+    }
+
+    impl MimeType for Plain {
+        fn mime_type(&self) -> Mime {
+            "text/plain".parse().unwrap()
+        }
+    }
+
+    #[test]
+    fn compress() {
+        let msg = "hello world".repeat(100);
+
+        for &encoding in &[ContentEncoding::Gzip, ContentEncoding::Deflate] {
+            let mut codec = Compressed::new(Plain, encoding).with_level(Compression::Best);
+            let mut buf = BytesMut::with_capacity(16);
+
+            codec.encode(msg.clone(), &mut buf).unwrap();
+
+            assert!(buf.len() < msg.len() / 10);
+            assert_eq!(codec.mime_type(), "text/plain".parse::<Mime>().unwrap());
+            assert_eq!(codec.content_encoding(), Some(encoding));
+
+            let mut s = String::new();
+
+            match encoding {
+                ContentEncoding::Gzip => GzDecoder::new(&buf[..]).unwrap().read_to_string(&mut s),
+                ContentEncoding::Deflate => ZlibDecoder::new(&buf[..]).read_to_string(&mut s),
+            }
+                    .unwrap();
+
+            assert_eq!(s, msg);
+        }
+
+        assert_eq!(Plain.content_encoding(), None);
+    }
+}
@@ -34,6 +34,16 @@ pub const WIRE_SEND: &'static str = "ws";
 /// A lag between wire receive and client or server receive might indicate queuing or processing delay.
 pub const WIRE_RECV: &'static str = "wr";
 
+/// The producer sent ("ms") a message to a broker.
+///
+/// The message delivery may be logged as a {@link #WIRE_SEND} annotation.
+pub const MESSAGE_SEND: &'static str = "ms";
+
+/// The consumer received ("mr") a message from a broker.
+///
+/// The message arrival may be logged as a {@link #WIRE_RECV} annotation.
+pub const MESSAGE_RECV: &'static str = "mr";
+
 /// Optionally logs progress of a ({@linkplain #CLIENT_SEND}, {@linkplain #WIRE_SEND}).
 ///
 /// For example, this could be one chunk in a chunked request.
@@ -73,11 +83,19 @@ pub const CLIENT_ADDR: &'static str = "ca";
 /// or fails to a different server ip or port.
 pub const SERVER_ADDR: &'static str = "sa";
 
+/// When present, {@link BinaryAnnotation#endpoint} indicates a message broker address ("ma") in a span.
+///
+/// Unlike client and server, there is no message broker annotation,
+/// so it is recorded by both the producer and consumer.
+pub const MESSAGE_ADDR: &'static str = "ma";
+
 /// Zipkin's core annotations indicate when a client or server operation began or ended.
 pub const CORE_ANNOTATIONS: &'static [&'static str] = &[CLIENT_SEND,
                                                         CLIENT_RECV,
                                                         SERVER_SEND,
                                                         SERVER_RECV,
+                                                        MESSAGE_SEND,
+                                                        MESSAGE_RECV,
                                                         WIRE_SEND,
                                                         WIRE_RECV,
                                                         CLIENT_SEND_FRAGMENT,
@@ -2,12 +2,25 @@ error_chain! {
     foreign_links {
         IoError(::std::io::Error);
         SystemTimeErro(::std::time::SystemTimeError);
+        RegexError(::regex::Error);
     }
     errors {
         SendError
         PoisonError {
             description("poisoned lock: another task failed inside")
         }
+        InvalidConfig(reason: String) {
+            description("invalid configuration")
+            display("invalid configuration: {}", reason)
+        }
+        InvalidId(id: String) {
+            description("invalid id")
+            display("invalid id `{}`", id)
+        }
+        InvalidHeader(name: &'static str, value: String) {
+            description("invalid header")
+            display("invalid header `{}`: {}", name, value)
+        }
     }
 }
 
@@ -0,0 +1,159 @@
+use std::fmt;
+use std::sync::Mutex;
+
+use rand::Rng;
+
+use xoroshiro128::{SeedableRng, Xoroshiro128Rng};
+
+use span::{Timestamp, TraceId, SpanId, next_id, now};
+
+/// Generate the trace and span ids
+pub trait IdGenerator: Send + Sync {
+    /// Generate a random 64-bit number
+    fn next_id(&self) -> u64;
+
+    /// Generate a non-zero span id
+    fn span_id(&self) -> SpanId {
+        loop {
+            let id = self.next_id();
+
+            if id != 0 {
+                return SpanId(id);
+            }
+        }
+    }
+
+    /// Generate a 128-bit trace id
+    fn trace_id(&self) -> TraceId {
+        TraceId {
+            lo: self.span_id().0,
+            hi: Some(self.span_id().0),
+        }
+    }
+
+    /// Generate a 128-bit trace id for the trace started at `timestamp`,
+    /// which the tracer reads from its clock.
+    fn trace_id_at(&self, _: Timestamp) -> TraceId {
+        self.trace_id()
+    }
+}
+
+impl fmt::Debug for IdGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("IdGenerator")
+    }
+}
+
+/// Generate the ids with the thread local random generator, it is the default one.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RandomIdGenerator;
+
+impl IdGenerator for RandomIdGenerator {
+    fn next_id(&self) -> u64 {
+        next_id()
+    }
+}
+
+/// Generate the same ids sequence from the seed, used for the tests.
+#[derive(Debug)]
+pub struct SeededIdGenerator {
+    rng: Mutex<Xoroshiro128Rng>,
+}
+
+impl SeededIdGenerator {
+    pub fn new(seed: u64) -> Self {
+        SeededIdGenerator { rng: Mutex::new(Xoroshiro128Rng::from_seed([seed, !seed])) }
+    }
+}
+
+impl IdGenerator for SeededIdGenerator {
+    fn next_id(&self) -> u64 {
+        self.rng.lock().unwrap().next_u64()
+    }
+}
+
+/// Generate the 64-bit trace ids for the legacy Zipkin storage.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TraceId64<G>(pub G);
+
+impl<G: IdGenerator> IdGenerator for TraceId64<G> {
+    fn next_id(&self) -> u64 {
+        self.0.next_id()
+    }
+
+    fn trace_id(&self) -> TraceId {
+        TraceId {
+            lo: self.span_id().0,
+            hi: None,
+        }
+    }
+}
+
+/// Generate the time-ordered 128-bit trace ids, which high 32 bits are the epoch seconds.
+///
+/// It is compatible with the trace id of AWS X-Ray.
+/// The tracer passes the start time from its clock, so a `ManualClock` makes the ids repeatable.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EpochTraceId<G>(pub G);
+
+impl<G: IdGenerator> IdGenerator for EpochTraceId<G> {
+    fn next_id(&self) -> u64 {
+        self.0.next_id()
+    }
+
+    fn trace_id(&self) -> TraceId {
+        self.trace_id_at(now())
+    }
+
+    fn trace_id_at(&self, timestamp: Timestamp) -> TraceId {
+        TraceId {
+            lo: self.span_id().0,
+            hi: Some((timestamp.sec as u64) << 32 | (self.next_id() & 0xFFFF_FFFF)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use span::timestamp;
+
+    #[test]
+    fn seeded() {
+        let (gen1, gen2) = (SeededIdGenerator::new(123), SeededIdGenerator::new(123));
+
+        for _ in 0..10 {
+            assert_eq!(gen1.span_id(), gen2.span_id());
+        }
+
+        assert_eq!(gen1.trace_id(), gen2.trace_id());
+        assert!(gen1.trace_id().hi.is_some());
+        assert!(SeededIdGenerator::new(456).span_id() != gen1.span_id());
+    }
+
+    #[test]
+    fn epoch_trace_id() {
+        let gen = EpochTraceId(RandomIdGenerator);
+        let start = now().sec as u64;
+        let trace_id = gen.trace_id();
+        let epoch = trace_id.hi.unwrap() >> 32;
+
+        assert!(start <= epoch && epoch <= now().sec as u64);
+        assert!(trace_id.lo != 0);
+
+        let (gen1, gen2) = (EpochTraceId(SeededIdGenerator::new(123)),
+                            EpochTraceId(SeededIdGenerator::new(123)));
+        let trace_id = gen1.trace_id_at(timestamp(1_500_000_000, 0));
+
+        assert_eq!(trace_id, gen2.trace_id_at(timestamp(1_500_000_000, 0)));
+        assert_eq!(trace_id.hi.unwrap() >> 32, 1_500_000_000);
+    }
+
+    #[test]
+    fn trace_id_64() {
+        let gen = TraceId64(RandomIdGenerator);
+
+        assert!(gen.span_id() != SpanId(0));
+        assert_eq!(gen.trace_id().hi, None);
+    }
+}
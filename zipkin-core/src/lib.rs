@@ -7,24 +7,59 @@ extern crate error_chain;
 extern crate lazy_static;
 extern crate time;
 extern crate rand;
+extern crate regex;
 extern crate xoroshiro128;
 extern crate bytes;
 extern crate tokio_io;
 extern crate mime;
+#[cfg(feature = "compress")]
+extern crate flate2;
+extern crate base64;
+extern crate arc_swap;
+#[cfg(feature = "serde")]
+extern crate serde;
 
 pub mod constants;
 pub mod errors;
 mod span;
+mod clock;
+mod id;
+mod xray;
 mod sampler;
+mod rule;
+mod adaptive;
+mod reload;
+mod processor;
+mod redact;
+mod limits;
 mod tracer;
 mod collector;
+#[cfg(feature = "compress")]
+mod compress;
+mod tail;
+#[cfg(feature = "serde")]
+mod serialize;
 
 pub use bytes::{BufMut, BytesMut};
-pub use tokio_io::codec::Encoder;
+pub use tokio_io::codec::{Encoder, Decoder};
 
 pub use constants::*;
 pub use span::{TraceId, SpanId, Timestamp, timestamp, now, ToMicrosecond, Duration, Endpoint,
-               Annotation, Value, BinaryAnnotation, BinaryAnnotationValue, Annotatable, Span};
-pub use sampler::{Sampler, FixedRate, RateLimit};
+               local_ip, Annotation, Value, BinaryAnnotation, BinaryAnnotationValue, Annotatable,
+               Kind, SpanContext, Span};
+pub use clock::{Clock, SystemClock, AnchoredClock, ManualClock, system_clock};
+pub use id::{IdGenerator, RandomIdGenerator, SeededIdGenerator, TraceId64, EpochTraceId};
+pub use xray::{X_AMZN_TRACE_ID, AmznTraceId};
+pub use sampler::{Sampler, BoxSampler, Decision, FixedRate, Probability, RateLimit, random};
+pub use rule::{Pattern, Rule, RuleSampler};
+pub use adaptive::Adaptive;
+pub use reload::Reloadable;
+pub use processor::{SpanProcessor, DefaultTags};
+pub use redact::Redaction;
+pub use limits::{Limits, Truncations, Truncate};
 pub use tracer::Tracer;
-pub use collector::{MimeType, Codec, Transport, Collector, BaseCollector};
\ No newline at end of file
+pub use collector::{ApiVersion, MimeType, ContentEncoding, Codec, Transport, Collector,
+                    BaseCollector};
+#[cfg(feature = "compress")]
+pub use compress::{Compressed, Compression};
+pub use tail::{Policy, TailSampling};
\ No newline at end of file
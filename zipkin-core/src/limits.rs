@@ -0,0 +1,322 @@
+use std::cmp;
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use constants::CORE_ANNOTATIONS;
+use span::{Value, Annotation, BinaryAnnotation, Span};
+use processor::SpanProcessor;
+
+/// The counters of the truncated or dropped parts of the spans
+#[derive(Debug, Default)]
+pub struct Truncations {
+    pub names: AtomicUsize,
+    pub keys: AtomicUsize,
+    pub values: AtomicUsize,
+    pub annotations: AtomicUsize,
+    pub binary_annotations: AtomicUsize,
+}
+
+impl Truncations {
+    pub fn total(&self) -> usize {
+        self.names.load(Ordering::Relaxed) + self.keys.load(Ordering::Relaxed) +
+        self.values.load(Ordering::Relaxed) +
+        self.annotations.load(Ordering::Relaxed) +
+        self.binary_annotations.load(Ordering::Relaxed)
+    }
+}
+
+/// The size limits of the span model.
+///
+/// The truncated strings are cut at the char boundary and end with the marker,
+/// which is counted in the limit. The binary values are cut without the marker,
+/// because it would corrupt the bytes, but they are still counted in `values`.
+///
+/// The core annotations are always kept, so Zipkin could still compute the duration,
+/// and only the other annotations count against `max_annotations`.
+///
+/// The cloned limits share the same truncation counters.
+#[derive(Clone, Debug)]
+pub struct Limits {
+    pub max_name_length: usize,
+    pub max_annotations: usize,
+    pub max_binary_annotations: usize,
+    pub max_key_length: usize,
+    pub max_value_length: usize,
+    /// Appended to the truncated names, keys and string values
+    pub marker: &'static str,
+    pub truncations: Arc<Truncations>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_name_length: 256,
+            max_annotations: 1024,
+            max_binary_annotations: 1024,
+            max_key_length: 256,
+            max_value_length: 64 * 1024,
+            marker: "...",
+            truncations: Arc::new(Truncations::default()),
+        }
+    }
+}
+
+impl Limits {
+    pub fn new() -> Self {
+        Limits::default()
+    }
+
+    pub fn with_max_name_length(self, max_name_length: usize) -> Self {
+        Limits { max_name_length: max_name_length, ..self }
+    }
+
+    pub fn with_max_annotations(self, max_annotations: usize) -> Self {
+        Limits { max_annotations: max_annotations, ..self }
+    }
+
+    pub fn with_max_binary_annotations(self, max_binary_annotations: usize) -> Self {
+        Limits { max_binary_annotations: max_binary_annotations, ..self }
+    }
+
+    pub fn with_max_key_length(self, max_key_length: usize) -> Self {
+        Limits { max_key_length: max_key_length, ..self }
+    }
+
+    pub fn with_max_value_length(self, max_value_length: usize) -> Self {
+        Limits { max_value_length: max_value_length, ..self }
+    }
+
+    pub fn with_marker(self, marker: &'static str) -> Self {
+        Limits { marker: marker, ..self }
+    }
+
+    fn truncate_value<'a>(&self, value: &mut Value<'a>) -> bool {
+        let truncated = match *value {
+            Value::Bytes(v) if v.len() > self.max_value_length => {
+                Value::Bytes(&v[..self.max_value_length])
+            }
+            Value::Blob(ref mut v) if v.len() > self.max_value_length => {
+                v.truncate(self.max_value_length);
+
+                return true;
+            }
+            Value::Str(_) |
+            Value::String(_) => {
+                match value.as_str() {
+                    Some(s) if s.len() > self.max_value_length => {
+                        Value::String(truncate_with(s, self.max_value_length, self.marker))
+                    }
+                    _ => return false,
+                }
+            }
+            _ => return false,
+        };
+
+        *value = truncated;
+
+        true
+    }
+}
+
+fn char_boundary(s: &str, max: usize) -> usize {
+    let mut pos = max;
+
+    while !s.is_char_boundary(pos) {
+        pos -= 1;
+    }
+
+    pos
+}
+
+/// Cut the string at the char boundary, and append the marker within the maximum length
+fn truncate_with(s: &str, max: usize, marker: &str) -> String {
+    let marker = &marker[..char_boundary(marker, cmp::min(marker.len(), max))];
+    let mut truncated = s[..char_boundary(s, max - marker.len())].to_owned();
+
+    truncated.push_str(marker);
+
+    truncated
+}
+
+fn truncate_str<'a>(s: &mut Cow<'a, str>, max: usize, marker: &str) -> bool {
+    if s.len() > max {
+        *s = Cow::Owned(truncate_with(s, max, marker));
+
+        true
+    } else {
+        false
+    }
+}
+
+fn count(counter: &AtomicUsize, n: usize) {
+    if n > 0 {
+        counter.fetch_add(n, Ordering::Relaxed);
+    }
+}
+
+/// Enforce the size limits
+pub trait Truncate {
+    fn enforce_limits(&mut self, limits: &Limits);
+}
+
+impl<'a> Truncate for Annotation<'a> {
+    fn enforce_limits(&mut self, limits: &Limits) {
+        if truncate_str(&mut self.value, limits.max_value_length, limits.marker) {
+            count(&limits.truncations.values, 1);
+        }
+    }
+}
+
+impl<'a> Truncate for BinaryAnnotation<'a> {
+    fn enforce_limits(&mut self, limits: &Limits) {
+        if truncate_str(&mut self.key, limits.max_key_length, limits.marker) {
+            count(&limits.truncations.keys, 1);
+        }
+        if limits.truncate_value(&mut self.value) {
+            count(&limits.truncations.values, 1);
+        }
+    }
+}
+
+impl<'a> Truncate for Span<'a> {
+    fn enforce_limits(&mut self, limits: &Limits) {
+        if truncate_str(&mut self.name, limits.max_name_length, limits.marker) {
+            count(&limits.truncations.names, 1);
+        }
+
+        let annotations = self.annotations.len();
+        let mut remaining = limits.max_annotations;
+
+        self.annotations
+            .retain(|annotation| if CORE_ANNOTATIONS.contains(&annotation.value.as_ref()) {
+                        true
+                    } else if remaining > 0 {
+                        remaining -= 1;
+                        true
+                    } else {
+                        false
+                    });
+        count(&limits.truncations.annotations,
+              annotations - self.annotations.len());
+
+        count(&limits.truncations.binary_annotations,
+              self.binary_annotations
+                  .len()
+                  .saturating_sub(limits.max_binary_annotations));
+        self.binary_annotations
+            .truncate(limits.max_binary_annotations);
+
+        for annotation in &mut self.annotations {
+            annotation.enforce_limits(limits);
+        }
+        for annotation in &mut self.binary_annotations {
+            annotation.enforce_limits(limits);
+        }
+    }
+}
+
+impl<T: Truncate> Truncate for Vec<T> {
+    fn enforce_limits(&mut self, limits: &Limits) {
+        for item in self {
+            item.enforce_limits(limits);
+        }
+    }
+}
+
+impl SpanProcessor for Limits {
+    fn on_end<'a>(&self, span: &mut Span<'a>) -> bool {
+        span.enforce_limits(self);
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::*;
+
+    #[test]
+    fn truncate() {
+        let limits = Limits::new()
+            .with_max_name_length(4)
+            .with_max_annotations(1)
+            .with_max_binary_annotations(2)
+            .with_max_key_length(8)
+            .with_max_value_length(12);
+        let body = [0u8; 16];
+
+        let mut span = Span::new("request");
+
+        span.annotate(CLIENT_SEND, None);
+        span.annotate("retry", None);
+        span.annotate("cache miss", None);
+        span.annotate(CLIENT_RECV, None);
+        span.binary_annotate("http.response.body", &body[..], None);
+        span.binary_annotate(HTTP_URL, "/users/\u{4f60}\u{597d}", None);
+        span.binary_annotate(HTTP_METHOD, "GET", None);
+
+        span.enforce_limits(&limits);
+
+        assert_eq!(span.name, "r...");
+        assert_eq!(span.annotations
+                       .iter()
+                       .map(|annotation| annotation.value.as_ref())
+                       .collect::<Vec<_>>(),
+                   vec![CLIENT_SEND, "retry", CLIENT_RECV]);
+        assert_eq!(span.binary_annotations.len(), 2);
+        assert_eq!(span.binary_annotations[0].key, "http....");
+        assert_eq!(span.binary_annotations[0].value, Value::Bytes(&body[..12]));
+        assert_eq!(span.binary_annotations[1].value,
+                   Value::String("/users/...".to_owned()));
+
+        let truncations = &limits.truncations;
+
+        assert_eq!(truncations.names.load(Ordering::Relaxed), 1);
+        assert_eq!(truncations.annotations.load(Ordering::Relaxed), 1);
+        assert_eq!(truncations.binary_annotations.load(Ordering::Relaxed), 1);
+        assert_eq!(truncations.keys.load(Ordering::Relaxed), 1);
+        assert_eq!(truncations.values.load(Ordering::Relaxed), 2);
+        assert_eq!(truncations.total(), 6);
+    }
+
+    #[test]
+    fn marker() {
+        let limits = Limits::new()
+            .with_max_value_length(4)
+            .with_marker("[truncated]");
+
+        let mut span = Span::new("test");
+
+        span.annotate("wire send", None);
+        span.binary_annotate(HTTP_URL, "/users", None);
+
+        span.enforce_limits(&limits);
+
+        assert_eq!(span.annotations[0].value, "[tru");
+        assert_eq!(span.binary_annotations[0].value, Value::String("[tru".to_owned()));
+
+        let limits = Limits::new().with_max_value_length(8);
+        let mut annotations = vec![Annotation {
+                                       timestamp: timestamp(0, 0),
+                                       value: "\u{4f60}\u{597d}\u{4f60}\u{597d}".into(),
+                                       endpoint: None,
+                                   }];
+
+        annotations.enforce_limits(&limits);
+
+        assert_eq!(annotations[0].value, "\u{4f60}...");
+        assert_eq!(limits.truncations.values.load(Ordering::Relaxed), 1);
+
+        // the binary values are cut without the marker, but still counted
+        let mut span = Span::new("test");
+
+        span.binary_annotate("http.request.body", vec![1u8; 16], None);
+
+        span.enforce_limits(&limits);
+
+        assert_eq!(span.binary_annotations[0].value, Value::Blob(vec![1u8; 8]));
+        assert_eq!(limits.truncations.values.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,71 @@
+use std::fmt;
+
+use span::{Value, Span, Annotatable};
+
+/// Hooks invoked by the `Tracer` between the span creation and submission.
+///
+/// It could be used to add default tags, enforce limits, redact or enrich the spans.
+pub trait SpanProcessor: Send + Sync {
+    /// Invoked when a recorded span was started.
+    fn on_start<'a>(&self, _span: &mut Span<'a>) {}
+
+    /// Invoked when a recorded span was finished, return `false` to drop the span.
+    fn on_end<'a>(&self, _span: &mut Span<'a>) -> bool {
+        true
+    }
+}
+
+impl fmt::Debug for SpanProcessor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SpanProcessor")
+    }
+}
+
+/// Add the default binary annotations to every started span, like the host or version.
+#[derive(Clone, Debug, Default)]
+pub struct DefaultTags {
+    pub tags: Vec<(&'static str, Value<'static>)>,
+}
+
+impl DefaultTags {
+    pub fn new() -> Self {
+        DefaultTags::default()
+    }
+
+    pub fn with<V: Into<Value<'static>>>(mut self, key: &'static str, value: V) -> Self {
+        self.tags.push((key, value.into()));
+        self
+    }
+}
+
+impl SpanProcessor for DefaultTags {
+    fn on_start<'a>(&self, span: &mut Span<'a>) {
+        for &(key, ref value) in &self.tags {
+            span.binary_annotate(key, value.clone(), None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_tags() {
+        let processor = DefaultTags::new()
+            .with("host", "localhost".to_owned())
+            .with("version", "1.0");
+
+        let mut span = Span::new("test");
+
+        processor.on_start(&mut span);
+
+        assert_eq!(span.binary_annotations.len(), 2);
+        assert_eq!(span.binary_annotations[0].key, "host");
+        assert_eq!(span.binary_annotations[0].value,
+                   Value::String("localhost".to_owned()));
+        assert_eq!(span.binary_annotations[1].key, "version");
+        assert_eq!(span.binary_annotations[1].value, Value::Str("1.0"));
+        assert!(processor.on_end(&mut span));
+    }
+}
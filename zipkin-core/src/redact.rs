@@ -0,0 +1,202 @@
+use regex::{Regex, NoExpand};
+
+use constants::{HTTP_URL, SQL_QUERY};
+use errors::Result;
+use rule::Pattern;
+use span::{Value, Span};
+use processor::SpanProcessor;
+
+lazy_static! {
+    static ref SQL_LITERAL: Regex = Regex::new(
+        r"'(?:[^']|'')*'|\b0[xX][0-9a-fA-F]+\b|\b\d+(?:\.\d+)?(?:[eE][+-]?\d+)?\b").unwrap();
+}
+
+/// Scrub the sensitive data from the binary annotations before they are encoded.
+///
+/// The keys on the deny list are dropped first, then the keys not on the allow list,
+/// if it was configured, and the remaining string values are masked.
+#[derive(Clone, Debug)]
+pub struct Redaction {
+    /// Drop the binary annotations which key matched any pattern
+    pub deny: Vec<Pattern>,
+    /// Keep only the binary annotations which key matched any pattern
+    pub allow: Option<Vec<Pattern>>,
+    /// Replace the matches in the string values with the mask
+    pub masks: Vec<Regex>,
+    pub mask: String,
+    /// Strip the query string and fragment from the `HTTP_URL`
+    pub strip_query: bool,
+    /// Replace the string and numeric literals in the `SQL_QUERY` with `?`
+    pub sql_literals: bool,
+}
+
+impl Default for Redaction {
+    fn default() -> Self {
+        Redaction {
+            deny: vec![],
+            allow: None,
+            masks: vec![],
+            mask: "***".to_owned(),
+            strip_query: true,
+            sql_literals: true,
+        }
+    }
+}
+
+impl Redaction {
+    pub fn new() -> Self {
+        Redaction::default()
+    }
+
+    pub fn deny<P: Into<Pattern>>(mut self, key: P) -> Self {
+        self.deny.push(key.into());
+        self
+    }
+
+    pub fn allow<P: Into<Pattern>>(mut self, key: P) -> Self {
+        self.allow
+            .get_or_insert_with(Vec::new)
+            .push(key.into());
+        self
+    }
+
+    /// Mask the matches of the regular expression, like the tokens or emails
+    pub fn mask(mut self, pattern: &str) -> Result<Self> {
+        self.masks.push(Regex::new(pattern)?);
+        Ok(self)
+    }
+
+    pub fn with_mask(self, mask: &str) -> Self {
+        Redaction { mask: mask.to_owned(), ..self }
+    }
+
+    pub fn with_strip_query(self, strip_query: bool) -> Self {
+        Redaction { strip_query: strip_query, ..self }
+    }
+
+    pub fn with_sql_literals(self, sql_literals: bool) -> Self {
+        Redaction { sql_literals: sql_literals, ..self }
+    }
+
+    fn is_kept(&self, key: &str) -> bool {
+        !self.deny.iter().any(|pattern| pattern.matches(key)) &&
+        self.allow
+            .as_ref()
+            .map_or(true, |allow| allow.iter().any(|pattern| pattern.matches(key)))
+    }
+
+    /// Scrub the binary annotations of the span
+    pub fn redact<'a>(&self, span: &mut Span<'a>) {
+        span.binary_annotations
+            .retain(|annotation| self.is_kept(&annotation.key));
+
+        for annotation in &mut span.binary_annotations {
+            let redacted = match annotation.value.as_str() {
+                Some(s) => self.redact_value(&annotation.key, s),
+                None => None,
+            };
+
+            if let Some(value) = redacted {
+                annotation.value = Value::String(value);
+            }
+        }
+    }
+
+    /// Return the redacted value if it was changed
+    fn redact_value(&self, key: &str, value: &str) -> Option<String> {
+        let mut redacted = None;
+
+        if self.strip_query && key == HTTP_URL {
+            if let Some(pos) = value.find(|c| c == '?' || c == '#') {
+                redacted = Some(value[..pos].to_owned());
+            }
+        }
+
+        if self.sql_literals && key == SQL_QUERY && SQL_LITERAL.is_match(value) {
+            redacted = Some(SQL_LITERAL.replace_all(value, "?").into_owned());
+        }
+
+        for re in &self.masks {
+            let masked = {
+                let value = redacted.as_ref().map_or(value, |s| s.as_str());
+
+                if re.is_match(value) {
+                    Some(re.replace_all(value, NoExpand(&self.mask)).into_owned())
+                } else {
+                    None
+                }
+            };
+
+            if masked.is_some() {
+                redacted = masked;
+            }
+        }
+
+        redacted
+    }
+}
+
+impl SpanProcessor for Redaction {
+    fn on_end<'a>(&self, span: &mut Span<'a>) -> bool {
+        self.redact(span);
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::*;
+
+    #[test]
+    fn redact() {
+        let redaction = Redaction::new()
+            .deny("password")
+            .deny("*.secret")
+            .mask(r"[\w.+-]+@[\w-]+\.[\w.]+")
+            .unwrap();
+
+        let mut span = Span::new("test");
+
+        span.binary_annotate("password", "123456", None);
+        span.binary_annotate("db.secret", "abc", None);
+        span.binary_annotate(HTTP_URL, "http://localhost/users?token=abc#top", None);
+        span.binary_annotate(SQL_QUERY,
+                             "SELECT * FROM t1 WHERE name = 'o''neil' AND age > 42 AND id = 0x1F",
+                             None);
+        span.binary_annotate("user", "contact bob@example.com", None);
+        span.binary_annotate(HTTP_STATUS_CODE, 200u16, None);
+
+        redaction.redact(&mut span);
+
+        let values = span.binary_annotations
+            .iter()
+            .map(|annotation| (&*annotation.key, annotation.value.clone()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(values,
+                   vec![(HTTP_URL, Value::String("http://localhost/users".to_owned())),
+                        (SQL_QUERY,
+                         Value::String("SELECT * FROM t1 WHERE name = ? AND age > ? AND id = ?"
+                                           .to_owned())),
+                        ("user", Value::String("contact ***".to_owned())),
+                        (HTTP_STATUS_CODE, Value::I16(200))]);
+    }
+
+    #[test]
+    fn allow() {
+        let redaction = Redaction::new()
+            .allow("http.*")
+            .with_strip_query(false);
+
+        let mut span = Span::new("test");
+
+        span.binary_annotate(HTTP_URL, "/users?id=1", None);
+        span.binary_annotate("user", "bob", None);
+
+        assert!(redaction.on_end(&mut span));
+        assert_eq!(span.binary_annotations.len(), 1);
+        assert_eq!(span.binary_annotations[0].value, Value::Str("/users?id=1"));
+    }
+}
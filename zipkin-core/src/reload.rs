@@ -0,0 +1,278 @@
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use arc_swap::ArcSwap;
+
+use sampler::{Sampler, BoxSampler, Decision};
+use errors::Result;
+
+/// Load the sampler from the content of configuration file
+pub type Loader<'a, T> = Box<Fn(&[u8]) -> Result<BoxSampler<'a, T>> + Send + Sync + 'a>;
+
+struct Inner<'a, T> {
+    path: PathBuf,
+    loader: Loader<'a, T>,
+    /// The replaced sampler is released when the last reader drops its reference.
+    current: ArcSwap<BoxSampler<'a, T>>,
+    modified: Mutex<Option<SystemTime>>,
+}
+
+impl<'a, T> Inner<'a, T> {
+    fn reload(&self) -> Result<bool> {
+        let modified = fs::metadata(&self.path)?.modified()?;
+
+        self.reload_modified(modified)
+    }
+
+    /// Reload the configuration unless it was loaded with the modification time,
+    /// which is only recorded after the new sampler has been swapped in,
+    /// so an invalid or half-written configuration will be retried.
+    fn reload_modified(&self, modified: SystemTime) -> Result<bool> {
+        let mut last_modified = self.modified.lock()?;
+
+        if *last_modified == Some(modified) {
+            return Ok(false);
+        }
+
+        let sampler = load(&self.path, &self.loader)?;
+
+        self.current.store(Arc::new(sampler));
+        *last_modified = Some(modified);
+
+        info!("reloaded sampling configuration from {:?}", self.path);
+
+        Ok(true)
+    }
+}
+
+fn load<'a, T>(path: &Path, loader: &Loader<'a, T>) -> Result<BoxSampler<'a, T>> {
+    let mut buf = vec![];
+
+    File::open(path)?.read_to_end(&mut buf)?;
+
+    loader(&buf)
+}
+
+/// Sampling with the configuration loaded from a file, which could be reloaded at runtime.
+///
+/// The underlying sampler is swapped atomically, the `sample` method borrows the current sampler
+/// without taking any lock, so a reload never blocks the sampling threads.
+pub struct Reloadable<'a, T> {
+    inner: Arc<Inner<'a, T>>,
+}
+
+impl<'a, T> Reloadable<'a, T> {
+    /// Load the sampler from the configuration file, the invalid configuration will be rejected.
+    pub fn open<P, F>(path: P, loader: F) -> Result<Self>
+        where P: AsRef<Path>,
+              F: 'a + Fn(&[u8]) -> Result<BoxSampler<'a, T>> + Send + Sync
+    {
+        let path = path.as_ref().to_owned();
+        let modified = fs::metadata(&path)?.modified()?;
+        let loader: Loader<'a, T> = Box::new(loader);
+        let sampler = load(&path, &loader)?;
+
+        info!("loaded sampling configuration from {:?}", path);
+
+        Ok(Reloadable {
+               inner: Arc::new(Inner {
+                                   path: path,
+                                   loader: loader,
+                                   current: ArcSwap::from(Arc::new(sampler)),
+                                   modified: Mutex::new(Some(modified)),
+                               }),
+           })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.inner.path
+    }
+
+    /// Reload the configuration if the file was modified since last loading.
+    ///
+    /// The current sampler will be kept if the new configuration is invalid.
+    pub fn reload(&self) -> Result<bool> {
+        self.inner.reload()
+    }
+}
+
+impl<T: 'static> Reloadable<'static, T> {
+    /// Watch the configuration file by polling its modification time,
+    /// the thread exits when the sampler has been dropped.
+    pub fn watch(&self, interval: Duration) -> JoinHandle<()> {
+        let inner = Arc::downgrade(&self.inner);
+
+        thread::spawn(move || loop {
+                          thread::sleep(interval);
+
+                          match inner.upgrade() {
+                              Some(inner) => {
+                                  if let Err(err) = inner.reload() {
+                                      warn!("fail to reload sampling configuration from {:?}, {}",
+                                            inner.path,
+                                            err);
+                                  }
+                              }
+                              None => break,
+                          }
+                      })
+    }
+}
+
+impl<'a, T> Sampler for Reloadable<'a, T> {
+    type Item = T;
+
+    fn sample(&self, item: &Self::Item) -> bool {
+        self.inner.current.lease().sample(item)
+    }
+
+    fn decide(&self, item: &Self::Item) -> Decision {
+        self.inner.current.lease().decide(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::prelude::*;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    use std::thread::{self, sleep};
+
+    use super::*;
+    use super::super::*;
+    use errors::ErrorKind;
+
+    fn loader(buf: &[u8]) -> Result<BoxSampler<'static, usize>> {
+        match buf {
+            b"always" => Ok(Box::new(Probability::new(1.0))),
+            b"never" => Ok(Box::new(Probability::new(0.0))),
+            _ => bail!(ErrorKind::InvalidConfig(String::from_utf8_lossy(buf).into_owned())),
+        }
+    }
+
+    fn write_config(path: &PathBuf, config: &str) {
+        File::create(path)
+            .unwrap()
+            .write_all(config.as_bytes())
+            .unwrap();
+    }
+
+    fn config_path() -> PathBuf {
+        env::temp_dir().join(format!("zipkin-sampling-{}.conf",
+                                     SystemTime::now()
+                                         .duration_since(UNIX_EPOCH)
+                                         .unwrap()
+                                         .subsec_nanos()))
+    }
+
+    #[test]
+    fn reload() {
+        let path = config_path();
+
+        write_config(&path, "unknown");
+
+        assert!(Reloadable::open(&path, loader).is_err());
+
+        write_config(&path, "always");
+
+        let sampler = Reloadable::open(&path, loader).unwrap();
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+
+        assert!(sampler.sample(&1));
+        assert!(!sampler.reload().unwrap());
+
+        // the modification time may have only one second resolution, so inject it
+        write_config(&path, "never");
+
+        assert!(sampler.inner.reload_modified(modified + Duration::from_secs(1)).unwrap());
+        assert!(!sampler.sample(&1));
+
+        let retained = sampler.inner.current.load();
+
+        write_config(&path, "unknown");
+
+        for _ in 0..2 {
+            assert!(sampler.inner.reload_modified(modified + Duration::from_secs(2)).is_err());
+            assert!(!sampler.sample(&1));
+        }
+
+        // the invalid configuration is retried with the same modification time
+        write_config(&path, "always");
+
+        assert!(sampler.inner.reload_modified(modified + Duration::from_secs(2)).unwrap());
+        assert!(sampler.sample(&1));
+
+        // the replaced sampler is released once its readers are done
+        assert_eq!(Arc::strong_count(&retained), 1);
+        assert!(!retained.sample(&1));
+
+        write_config(&path, "never");
+
+        let watcher = sampler.watch(Duration::from_millis(10));
+
+        sleep(Duration::from_millis(100));
+
+        assert!(!sampler.sample(&1));
+
+        drop(sampler);
+
+        watcher.join().unwrap();
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reload_while_sampling() {
+        let path = config_path();
+
+        write_config(&path, "always");
+
+        let sampler = Arc::new(Reloadable::open(&path, loader).unwrap());
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let samplers = (0..4)
+            .map(|_| {
+                let sampler = sampler.clone();
+                let stopped = stopped.clone();
+
+                thread::spawn(move || {
+                    let mut sampled = 0;
+
+                    while !stopped.load(Ordering::Relaxed) {
+                        sampler.sample(&1);
+                        sampled += 1;
+                    }
+
+                    sampled
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for i in 1..101 {
+            write_config(&path, if i % 2 == 0 { "always" } else { "never" });
+
+            assert!(sampler
+                        .inner
+                        .reload_modified(modified + Duration::from_secs(i))
+                        .unwrap());
+        }
+
+        stopped.store(true, Ordering::Relaxed);
+
+        for handle in samplers {
+            assert!(handle.join().unwrap() > 0);
+        }
+
+        assert!(sampler.sample(&1));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
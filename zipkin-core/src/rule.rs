@@ -0,0 +1,285 @@
+use std::fmt;
+
+use sampler::{Sampler, BoxSampler, Decision};
+use span::Span;
+
+const WILDCARDS: &'static [char] = &['*', '?'];
+
+/// A pattern to match the span name, service name or binary annotation value
+#[derive(Clone, Debug, PartialEq)]
+pub enum Pattern {
+    /// Match any value
+    Any,
+    /// Match the exact value
+    Exact(String),
+    /// Match values start with the prefix, like `/api/*`
+    Prefix(String),
+    /// Match values with the glob wildcards, `*` for any characters and `?` for one character
+    Glob(String),
+}
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Pattern {
+        if pattern == "*" {
+            Pattern::Any
+        } else if !pattern.contains(WILDCARDS) {
+            Pattern::Exact(pattern.to_owned())
+        } else if pattern.ends_with('*') &&
+                  !pattern[..pattern.len() - 1].contains(WILDCARDS) {
+            Pattern::Prefix(pattern[..pattern.len() - 1].to_owned())
+        } else {
+            Pattern::Glob(pattern.to_owned())
+        }
+    }
+
+    pub fn matches(&self, value: &str) -> bool {
+        match *self {
+            Pattern::Any => true,
+            Pattern::Exact(ref s) => s == value,
+            Pattern::Prefix(ref s) => value.starts_with(s.as_str()),
+            Pattern::Glob(ref s) => glob_match(s, value),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Pattern {
+    fn from(pattern: &'a str) -> Self {
+        Pattern::new(pattern)
+    }
+}
+
+fn glob_match(pattern: &str, value: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<char>>();
+    let value = value.chars().collect::<Vec<char>>();
+
+    let (mut p, mut v) = (0, 0);
+    let mut backtrack = None;
+
+    while v < value.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, v));
+            p += 1;
+        } else if p < pattern.len() && (pattern[p] == '?' || pattern[p] == value[v]) {
+            p += 1;
+            v += 1;
+        } else if let Some((star, matched)) = backtrack {
+            backtrack = Some((star, matched + 1));
+            p = star + 1;
+            v = matched + 1;
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// A sampling rule, the span must match all the patterns to use the rule's sampler.
+#[derive(Debug)]
+pub struct Rule<'a> {
+    /// Pattern of the span name
+    pub name: Option<Pattern>,
+    /// Pattern of the service name in the endpoints of the span
+    pub service: Option<Pattern>,
+    /// Patterns of the initial binary annotations, like `HTTP_PATH` or `HTTP_METHOD`
+    pub tags: Vec<(String, Pattern)>,
+    /// The sampler to decide the matched spans
+    pub sampler: BoxSampler<'a, Span<'a>>,
+}
+
+impl<'a> Rule<'a> {
+    pub fn new(sampler: BoxSampler<'a, Span<'a>>) -> Self {
+        Rule {
+            name: None,
+            service: None,
+            tags: vec![],
+            sampler: sampler,
+        }
+    }
+
+    pub fn with_name<P: Into<Pattern>>(self, pattern: P) -> Self {
+        Rule { name: Some(pattern.into()), ..self }
+    }
+
+    pub fn with_service<P: Into<Pattern>>(self, pattern: P) -> Self {
+        Rule { service: Some(pattern.into()), ..self }
+    }
+
+    pub fn with_tag<P: Into<Pattern>>(mut self, key: &str, pattern: P) -> Self {
+        self.tags.push((key.to_owned(), pattern.into()));
+        self
+    }
+
+    pub fn matches(&self, span: &Span<'a>) -> bool {
+        self.name
+            .as_ref()
+            .map_or(true, |pattern| pattern.matches(&span.name)) &&
+        self.service
+            .as_ref()
+            .map_or(true, |pattern| {
+                service_names(span).any(|name| pattern.matches(name))
+            }) &&
+        self.tags
+            .iter()
+            .all(|&(ref key, ref pattern)| {
+                span.binary_annotations
+                    .iter()
+                    .filter(|annotation| annotation.key == key.as_str())
+                    .filter_map(|annotation| annotation.value.as_str())
+                    .any(|value| pattern.matches(value))
+            })
+    }
+}
+
+fn service_names<'b, 'a: 'b>(span: &'b Span<'a>) -> Box<Iterator<Item = &'b str> + 'b> {
+    Box::new(span.annotations
+                 .iter()
+                 .flat_map(|annotation| annotation.endpoint.as_ref())
+                 .chain(span.binary_annotations
+                            .iter()
+                            .flat_map(|annotation| annotation.endpoint.as_ref()))
+                 .chain(span.local_endpoint.as_ref())
+                 .flat_map(|endpoint| endpoint.name.as_ref().map(|name| &**name)))
+}
+
+/// Rule based sampling
+///
+/// The rules are evaluated in order, the first matched rule decides the span,
+/// or the default sampler will be used if no rule matched.
+#[derive(Debug)]
+pub struct RuleSampler<'a> {
+    pub rules: Vec<Rule<'a>>,
+    pub default: Option<BoxSampler<'a, Span<'a>>>,
+}
+
+impl<'a> fmt::Debug for BoxSampler<'a, Span<'a>> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Sampler")
+    }
+}
+
+impl<'a> Default for RuleSampler<'a> {
+    fn default() -> Self {
+        RuleSampler::new()
+    }
+}
+
+impl<'a> RuleSampler<'a> {
+    pub fn new() -> Self {
+        RuleSampler {
+            rules: vec![],
+            default: None,
+        }
+    }
+
+    pub fn with_default(default: BoxSampler<'a, Span<'a>>) -> Self {
+        RuleSampler {
+            rules: vec![],
+            default: Some(default),
+        }
+    }
+
+    pub fn rule(mut self, rule: Rule<'a>) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    fn sampler(&self, span: &Span<'a>) -> Option<&(Sampler<Item = Span<'a>> + 'a)> {
+        if let Some(rule) = self.rules.iter().find(|rule| rule.matches(span)) {
+            Some(rule.sampler.as_ref())
+        } else {
+            self.default.as_ref().map(|sampler| sampler.as_ref())
+        }
+    }
+}
+
+impl<'a> Sampler for RuleSampler<'a> {
+    type Item = Span<'a>;
+
+    fn sample(&self, span: &Self::Item) -> bool {
+        self.sampler(span)
+            .map_or(true, |sampler| sampler.sample(span))
+    }
+
+    fn decide(&self, span: &Self::Item) -> Decision {
+        self.sampler(span)
+            .map_or(Decision::Sample, |sampler| sampler.decide(span))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use super::super::*;
+
+    #[test]
+    fn pattern() {
+        assert_eq!(Pattern::new("*"), Pattern::Any);
+        assert_eq!(Pattern::new("/checkout"), Pattern::Exact("/checkout".to_owned()));
+        assert_eq!(Pattern::new("/api/*"), Pattern::Prefix("/api/".to_owned()));
+        assert_eq!(Pattern::new("/api/*/orders"),
+                   Pattern::Glob("/api/*/orders".to_owned()));
+
+        assert!(Pattern::new("/checkout").matches("/checkout"));
+        assert!(!Pattern::new("/checkout").matches("/checkout/1"));
+        assert!(Pattern::new("/api/*").matches("/api/users"));
+        assert!(!Pattern::new("/api/*").matches("/health"));
+        assert!(Pattern::new("/api/*/orders").matches("/api/v1/orders"));
+        assert!(Pattern::new("/api/*/orders").matches("/api/v1/users/orders"));
+        assert!(!Pattern::new("/api/*/orders").matches("/api/v1/orders/1"));
+        assert!(Pattern::new("ping-??").matches("ping-01"));
+        assert!(!Pattern::new("ping-??").matches("ping-1"));
+        assert!(Pattern::new("*ping*").matches("internal-ping-check"));
+        assert!(Pattern::new("a*b").matches("a*xb"));
+        assert!(Pattern::new("a*b").matches("a*b"));
+        assert!(!Pattern::new("a*b").matches("a*x"));
+    }
+
+    #[test]
+    fn rule_sampler() {
+        let sampler = RuleSampler::with_default(Box::new(FixedRate::new(2)))
+            .rule(Rule::new(Box::new(Probability::new(1.0))).with_tag(HTTP_PATH, "/checkout"))
+            .rule(Rule::new(Box::new(Probability::new(0.0)))
+                      .with_name("ping*")
+                      .with_service("internal-*"))
+            .rule(Rule::new(Box::new(Probability::new(0.0)))
+                      .with_tag(HTTP_METHOD, "GET")
+                      .with_tag(HTTP_PATH, "/health"));
+
+        let mut span = Span::new("request");
+
+        span.binary_annotate(HTTP_PATH, "/checkout", None);
+
+        assert!(sampler.sample(&span));
+        assert!(sampler.sample(&span));
+
+        let mut span = Span::new("ping");
+
+        assert!(sampler.sample(&span));
+        assert!(!sampler.sample(&span));
+
+        span.annotate(SERVER_RECV,
+                      Some(Arc::new(Endpoint {
+                                        name: Some("internal-api".into()),
+                                        addr: None,
+                                    })));
+
+        assert!(!sampler.sample(&span));
+        assert!(!sampler.sample(&span));
+
+        let mut span = Span::new("request");
+
+        span.binary_annotate(HTTP_PATH, "/health", None);
+
+        assert!(sampler.sample(&span));
+
+        span.binary_annotate(HTTP_METHOD, "GET", None);
+
+        assert!(!sampler.sample(&span));
+        assert!(!sampler.sample(&span));
+
+        assert!(RuleSampler::new().sample(&Span::new("test")));
+    }
+}
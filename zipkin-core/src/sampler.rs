@@ -4,10 +4,80 @@ use std::time::{Duration, Instant};
 use std::sync::Mutex;
 use std::sync::atomic::{AtomicUsize, AtomicIsize, Ordering};
 
+use span::next_id;
+
+/// The outcome of the sampling policy
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Decision {
+    /// The span is neither recorded nor exported.
+    Drop,
+    /// The span is recorded locally, but not exported to the collector.
+    RecordOnly,
+    /// The span is recorded and exported to the collector.
+    Sample,
+    /// The span is recorded and exported, and the collector should store it even if it overrides sampling policy.
+    Debug,
+    /// The decision is deferred to the downstream, the span is recorded and exported.
+    Defer,
+}
+
+impl Decision {
+    /// The span should be recorded, annotations will be added to it.
+    pub fn is_recorded(&self) -> bool {
+        *self != Decision::Drop
+    }
+
+    /// The span should be submitted to the collector.
+    pub fn is_exported(&self) -> bool {
+        match *self {
+            Decision::Drop | Decision::RecordOnly => false,
+            Decision::Sample | Decision::Debug | Decision::Defer => true,
+        }
+    }
+
+    /// The `sampled` flag propagated to the downstream.
+    pub fn sampled(&self) -> Option<bool> {
+        match *self {
+            Decision::Drop | Decision::RecordOnly => Some(false),
+            Decision::Sample | Decision::Debug => Some(true),
+            Decision::Defer => None,
+        }
+    }
+}
+
+impl From<bool> for Decision {
+    fn from(sampled: bool) -> Self {
+        if sampled {
+            Decision::Sample
+        } else {
+            Decision::Drop
+        }
+    }
+}
+
 pub trait Sampler: Send + Sync {
     type Item;
 
     fn sample(&self, item: &Self::Item) -> bool;
+
+    /// Make a sampling decision, the samplers may override it to return a richer outcome.
+    fn decide(&self, item: &Self::Item) -> Decision {
+        self.sample(item).into()
+    }
+}
+
+pub type BoxSampler<'a, T> = Box<Sampler<Item = T> + 'a>;
+
+impl<S: Sampler + ?Sized> Sampler for Box<S> {
+    type Item = S::Item;
+
+    fn sample(&self, item: &Self::Item) -> bool {
+        (**self).sample(item)
+    }
+
+    fn decide(&self, item: &Self::Item) -> Decision {
+        (**self).decide(item)
+    }
 }
 
 /// Fixed rate sampling
@@ -45,6 +115,35 @@ impl<T> Sampler for FixedRate<T> {
     }
 }
 
+/// Probabilistic sampling
+#[derive(Debug)]
+pub struct Probability<T> {
+    pub probability: f64,
+    phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> Probability<T> {
+    pub fn new(probability: f64) -> Self {
+        Probability {
+            probability: probability,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> Sampler for Probability<T> {
+    type Item = T;
+
+    fn sample(&self, _: &Self::Item) -> bool {
+        random() < self.probability
+    }
+}
+
+/// Generate a uniformly distributed random number in `[0, 1)`
+pub fn random() -> f64 {
+    (next_id() >> 11) as f64 / (1u64 << 53) as f64
+}
+
 /// Rate limiting with token bucket
 #[derive(Debug)]
 pub struct RateLimit<T> {
@@ -124,6 +223,41 @@ mod tests {
 
     use super::*;
 
+    struct MockSampler;
+
+    impl Sampler for MockSampler {
+        type Item = usize;
+
+        fn sample(&self, item: &usize) -> bool {
+            *item > 0
+        }
+
+        fn decide(&self, item: &usize) -> Decision {
+            match *item {
+                0 => Decision::Drop,
+                1 => Decision::RecordOnly,
+                _ => Decision::Debug,
+            }
+        }
+    }
+
+    #[test]
+    fn decision() {
+        assert_eq!(FixedRate::new(2).decide(&1), Decision::Sample);
+        assert_eq!(Probability::new(0.0).decide(&1), Decision::Drop);
+
+        assert_eq!(MockSampler.decide(&1), Decision::RecordOnly);
+        assert_eq!(MockSampler.decide(&2), Decision::Debug);
+
+        assert!(!Decision::Drop.is_recorded());
+        assert!(Decision::RecordOnly.is_recorded());
+        assert!(!Decision::RecordOnly.is_exported());
+        assert!(Decision::Defer.is_exported());
+        assert_eq!(Decision::RecordOnly.sampled(), Some(false));
+        assert_eq!(Decision::Debug.sampled(), Some(true));
+        assert_eq!(Decision::Defer.sampled(), None);
+    }
+
     #[test]
     fn fixed_rate() {
         let sampler = FixedRate::new(3);
@@ -143,6 +277,17 @@ mod tests {
                 .unwrap();
     }
 
+    #[test]
+    fn probability() {
+        assert!(!Probability::new(0.0).sample(&1));
+        assert!(Probability::new(1.0).sample(&1));
+
+        let sampler = Probability::new(0.5);
+        let sampled = (0..1000).filter(|i| sampler.sample(i)).count();
+
+        assert!(300 < sampled && sampled < 700);
+    }
+
     #[test]
     fn rate_limit() {
         let sampler = RateLimit::new(1, 2, Duration::from_millis(100));
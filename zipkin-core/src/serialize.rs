@@ -0,0 +1,558 @@
+//! Serialize and deserialize the span model in the Zipkin v1 JSON wire shape.
+//!
+//! The serialized object keys are sorted, and `zipkin-json` encodes the v1 JSON with them.
+//!
+//! The deserialized spans borrow the strings from the input if possible,
+//! and own the strings which contain the escaped characters.
+
+use std::fmt;
+use std::str;
+use std::{i16, i32, i64};
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::net::{SocketAddr, IpAddr, Ipv4Addr, Ipv6Addr};
+
+use base64;
+
+use serde::ser::{self, Serialize, Serializer, SerializeMap};
+use serde::de::{self, Deserialize, Deserializer, Visitor, MapAccess, IgnoredAny};
+
+use span::{TraceId, SpanId, Timestamp, ToMicrosecond, Duration, Endpoint, Annotation, Value,
+           BinaryAnnotation, Span, timestamp};
+
+fn from_microseconds(us: i64) -> Timestamp {
+    let (sec, us) = (us / 1000_000, us % 1000_000);
+
+    if us < 0 {
+        timestamp(sec - 1, ((us + 1000_000) * 1000) as i32)
+    } else {
+        timestamp(sec, (us * 1000) as i32)
+    }
+}
+
+const HEX_DIGITS: &'static [u8; 16] = b"0123456789abcdef";
+
+/// Hex encode the id into the buffer, padded with zeros.
+fn write_hex(buf: &mut [u8], id: u64) {
+    for (i, b) in buf.iter_mut().rev().enumerate() {
+        *b = HEX_DIGITS[(id >> (i * 4)) as usize & 0xF];
+    }
+}
+
+fn serialize_hex<S: Serializer>(serializer: S, buf: &[u8]) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(str::from_utf8(buf).map_err(ser::Error::custom)?)
+}
+
+impl Serialize for TraceId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut buf = [0u8; 32];
+
+        match self.hi {
+            Some(hi) => {
+                write_hex(&mut buf[..16], hi);
+                write_hex(&mut buf[16..], self.lo);
+
+                serialize_hex(serializer, &buf)
+            }
+            None => {
+                write_hex(&mut buf[..16], self.lo);
+
+                serialize_hex(serializer, &buf[..16])
+            }
+        }
+    }
+}
+
+impl Serialize for SpanId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut buf = [0u8; 16];
+
+        write_hex(&mut buf, self.0);
+
+        serialize_hex(serializer, &buf)
+    }
+}
+
+impl<'a> Serialize for Endpoint<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+
+        if let Some(addr) = self.addr {
+            match addr.ip() {
+                IpAddr::V4(ip) => map.serialize_entry("ipv4", &ip)?,
+                IpAddr::V6(ip) => map.serialize_entry("ipv6", &ip)?,
+            }
+
+            if addr.port() > 0 {
+                map.serialize_entry("port", &addr.port())?;
+            }
+        }
+        if let Some(ref name) = self.name {
+            map.serialize_entry("serviceName", &**name)?;
+        }
+
+        map.end()
+    }
+}
+
+impl<'a> Serialize for Annotation<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+
+        if let Some(ref endpoint) = self.endpoint {
+            map.serialize_entry("endpoint", endpoint.as_ref())?;
+        }
+        map.serialize_entry("timestamp", &self.timestamp.to_microseconds())?;
+        map.serialize_entry("value", &*self.value)?;
+
+        map.end()
+    }
+}
+
+impl<'a> Serialize for Value<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            Value::Bool(v) => serializer.serialize_bool(v),
+            Value::Bytes(v) => serializer.serialize_str(&base64::encode(v)),
+            Value::Blob(ref v) => serializer.serialize_str(&base64::encode(v)),
+            Value::I16(v) => serializer.serialize_i16(v),
+            Value::I32(v) => serializer.serialize_i32(v),
+            Value::I64(v) => serializer.serialize_i64(v),
+            Value::Double(v) => serializer.serialize_f64(v),
+            Value::Str(v) => serializer.serialize_str(v),
+            Value::String(ref v) => serializer.serialize_str(v),
+        }
+    }
+}
+
+/// The `type` of the binary annotation value, or None for the string and bool values.
+fn value_type(value: &Value) -> Option<&'static str> {
+    match *value {
+        Value::Bytes(_) | Value::Blob(_) => Some("BYTES"),
+        Value::I16(_) => Some("I16"),
+        Value::I32(_) => Some("I32"),
+        Value::I64(_) => Some("I64"),
+        Value::Double(_) => Some("DOUBLE"),
+        Value::Bool(_) | Value::Str(_) | Value::String(_) => None,
+    }
+}
+
+impl<'a> Serialize for BinaryAnnotation<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+
+        if let Some(ref endpoint) = self.endpoint {
+            map.serialize_entry("endpoint", endpoint.as_ref())?;
+        }
+        map.serialize_entry("key", &*self.key)?;
+        if let Some(ty) = value_type(&self.value) {
+            map.serialize_entry("type", ty)?;
+        }
+        map.serialize_entry("value", &self.value)?;
+
+        map.end()
+    }
+}
+
+impl<'a> Serialize for Span<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+
+        if !self.annotations.is_empty() {
+            map.serialize_entry("annotations", &self.annotations)?;
+        }
+        let remote_address = self.remote_address();
+        if !self.binary_annotations.is_empty() || remote_address.is_some() {
+            map.serialize_entry("binaryAnnotations",
+                                &BinaryAnnotations(&self.binary_annotations,
+                                                   remote_address.as_ref()))?;
+        }
+        if let Some(debug) = self.debug {
+            map.serialize_entry("debug", &debug)?;
+        }
+        if let Some(d) = self.duration {
+            map.serialize_entry("duration", &d.to_microseconds())?;
+        }
+        map.serialize_entry("id", &self.id)?;
+        map.serialize_entry("name", &*self.name)?;
+        if let Some(ref id) = self.parent_id {
+            map.serialize_entry("parentId", id)?;
+        }
+        map.serialize_entry("timestamp", &self.timestamp.to_microseconds())?;
+        map.serialize_entry("traceId", &self.trace_id)?;
+
+        map.end()
+    }
+}
+
+/// The binary annotations followed by the address annotation of the remote endpoint
+struct BinaryAnnotations<'a, 'b: 'a>(&'a [BinaryAnnotation<'b>], Option<&'a BinaryAnnotation<'b>>);
+
+impl<'a, 'b> Serialize for BinaryAnnotations<'a, 'b> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().chain(self.1))
+    }
+}
+
+/// The map key or string field, borrowed from the input if possible
+struct Key<'de>(Cow<'de, str>);
+
+impl<'de> Deserialize<'de> for Key<'de> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct KeyVisitor;
+
+        impl<'de> Visitor<'de> for KeyVisitor {
+            type Value = Key<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a field name")
+            }
+
+            fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+                Ok(Key(Cow::Borrowed(v)))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(Key(Cow::Owned(v.to_owned())))
+            }
+
+            fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+                Ok(Key(Cow::Owned(v)))
+            }
+        }
+
+        deserializer.deserialize_str(KeyVisitor)
+    }
+}
+
+fn missing<T, E: de::Error>(field: Option<T>, name: &'static str) -> Result<T, E> {
+    field.ok_or_else(|| de::Error::missing_field(name))
+}
+
+struct IdVisitor<T>(&'static str, fn(&str) -> Option<T>);
+
+impl<'de, T> Visitor<'de> for IdVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.0)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        (self.1)(v).ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(v), &self))
+    }
+}
+
+impl<'de> Deserialize<'de> for TraceId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(IdVisitor("a 16 or 32 hex characters trace id",
+                                               |s| s.parse().ok()))
+    }
+}
+
+impl<'de> Deserialize<'de> for SpanId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(IdVisitor("a 16 hex characters span id", |s| s.parse().ok()))
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Endpoint<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EndpointVisitor;
+
+        impl<'de> Visitor<'de> for EndpointVisitor {
+            type Value = Endpoint<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an endpoint")
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Self::Value, M::Error> {
+                let mut name = None;
+                let mut ip = None;
+                let mut port = None;
+
+                while let Some(key) = map.next_key::<Key>()? {
+                    match key.0.as_ref() {
+                        "serviceName" => name = map.next_value::<Option<Key>>()?.map(|k| k.0),
+                        "ipv4" => {
+                            ip = map.next_value::<Option<Ipv4Addr>>()?
+                                .map(IpAddr::V4)
+                                .or(ip)
+                        }
+                        "ipv6" => {
+                            ip = map.next_value::<Option<Ipv6Addr>>()?
+                                .map(IpAddr::V6)
+                                .or(ip)
+                        }
+                        "port" => port = map.next_value()?,
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+
+                Ok(Endpoint {
+                       name: name,
+                       addr: ip.map(|ip| SocketAddr::new(ip, port.unwrap_or(0))),
+                   })
+            }
+        }
+
+        deserializer.deserialize_map(EndpointVisitor)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Annotation<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct AnnotationVisitor;
+
+        impl<'de> Visitor<'de> for AnnotationVisitor {
+            type Value = Annotation<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an annotation")
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Self::Value, M::Error> {
+                let mut ts = None;
+                let mut value = None;
+                let mut endpoint = None;
+
+                while let Some(key) = map.next_key::<Key>()? {
+                    match key.0.as_ref() {
+                        "timestamp" => ts = Some(map.next_value()?),
+                        "value" => value = Some(map.next_value::<Key>()?.0),
+                        "endpoint" => endpoint = map.next_value::<Option<Endpoint>>()?,
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+
+                Ok(Annotation {
+                       timestamp: from_microseconds(missing(ts, "timestamp")?),
+                       value: missing(value, "value")?,
+                       endpoint: endpoint.map(Arc::new),
+                   })
+            }
+        }
+
+        deserializer.deserialize_map(AnnotationVisitor)
+    }
+}
+
+/// Deserialize the JSON value, the `I16`, `I32`, `DOUBLE` and `BYTES` values
+/// are restored by the `type` of the binary annotation.
+impl<'de: 'a, 'a> Deserialize<'de> for Value<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ValueVisitor;
+
+        impl<'de> Visitor<'de> for ValueVisitor {
+            type Value = Value<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a bool, number or string value")
+            }
+
+            fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+                Ok(Value::Bool(v))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                Ok(Value::I64(v))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                if v <= i64::MAX as u64 {
+                    Ok(Value::I64(v as i64))
+                } else {
+                    Err(E::invalid_value(de::Unexpected::Unsigned(v), &"a 64-bit integer"))
+                }
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+                Ok(Value::Double(v))
+            }
+
+            fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+                Ok(Value::Str(v))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(Value::String(v.to_owned()))
+            }
+
+            fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+                Ok(Value::String(v))
+            }
+        }
+
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+fn typed_value<'a, E: de::Error>(value: Value<'a>, ty: Option<&str>) -> Result<Value<'a>, E> {
+    let invalid = |value: &Value| {
+        de::Error::custom(format_args!("invalid {} value: {:?}", ty.unwrap_or("STRING"), value))
+    };
+
+    match (ty, value) {
+        (None, value) |
+        (Some("BOOL"), value @ Value::Bool(_)) |
+        (Some("STRING"), value @ Value::Str(_)) |
+        (Some("STRING"), value @ Value::String(_)) |
+        (Some("I64"), value @ Value::I64(_)) |
+        (Some("DOUBLE"), value @ Value::Double(_)) => Ok(value),
+        (Some("I16"), Value::I64(v)) if v < i16::MIN as i64 || v > i16::MAX as i64 => {
+            Err(de::Error::invalid_value(de::Unexpected::Signed(v), &"a 16-bit integer"))
+        }
+        (Some("I32"), Value::I64(v)) if v < i32::MIN as i64 || v > i32::MAX as i64 => {
+            Err(de::Error::invalid_value(de::Unexpected::Signed(v), &"a 32-bit integer"))
+        }
+        (Some("I16"), Value::I64(v)) => Ok(Value::I16(v as i16)),
+        (Some("I32"), Value::I64(v)) => Ok(Value::I32(v as i32)),
+        (Some("DOUBLE"), Value::I64(v)) => Ok(Value::Double(v as f64)),
+        (Some("BYTES"), value) => {
+            let bytes = match value {
+                Value::Str(s) => base64::decode(s).ok(),
+                Value::String(ref s) => base64::decode(s).ok(),
+                _ => None,
+            };
+
+            bytes.map(Value::Blob).ok_or_else(|| invalid(&value))
+        }
+        (Some(_), value) => Err(invalid(&value)),
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for BinaryAnnotation<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BinaryAnnotationVisitor;
+
+        impl<'de> Visitor<'de> for BinaryAnnotationVisitor {
+            type Value = BinaryAnnotation<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a binary annotation")
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Self::Value, M::Error> {
+                let mut key = None;
+                let mut value = None;
+                let mut ty = None;
+                let mut endpoint = None;
+
+                while let Some(k) = map.next_key::<Key>()? {
+                    match k.0.as_ref() {
+                        "key" => key = Some(map.next_value::<Key>()?.0),
+                        "value" => value = Some(map.next_value()?),
+                        "type" => ty = map.next_value::<Option<Key>>()?,
+                        "endpoint" => endpoint = map.next_value::<Option<Endpoint>>()?,
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+
+                Ok(BinaryAnnotation {
+                       key: missing(key, "key")?,
+                       value: typed_value(missing(value, "value")?,
+                                          ty.as_ref().map(|ty| ty.0.as_ref()))?,
+                       endpoint: endpoint.map(Arc::new),
+                   })
+            }
+        }
+
+        deserializer.deserialize_map(BinaryAnnotationVisitor)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Span<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SpanVisitor;
+
+        impl<'de> Visitor<'de> for SpanVisitor {
+            type Value = Span<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a span")
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Self::Value, M::Error> {
+                let mut trace_id = None;
+                let mut id = None;
+                let mut name = None;
+                let mut parent_id = None;
+                let mut ts = None;
+                let mut duration = None;
+                let mut annotations = None;
+                let mut binary_annotations = None;
+                let mut debug = None;
+
+                while let Some(key) = map.next_key::<Key>()? {
+                    match key.0.as_ref() {
+                        "traceId" => trace_id = Some(map.next_value()?),
+                        "id" => id = Some(map.next_value()?),
+                        "name" => name = Some(map.next_value::<Key>()?.0),
+                        "parentId" => parent_id = map.next_value()?,
+                        "timestamp" => ts = map.next_value::<Option<i64>>()?,
+                        "duration" => duration = map.next_value::<Option<i64>>()?,
+                        "annotations" => annotations = map.next_value()?,
+                        "binaryAnnotations" => binary_annotations = map.next_value()?,
+                        "debug" => debug = map.next_value()?,
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+
+                let mut span = Span::from_parts(missing(trace_id, "traceId")?,
+                                                missing(id, "id")?,
+                                                missing(name, "name")?,
+                                                from_microseconds(missing(ts, "timestamp")?));
+
+                span.parent_id = parent_id;
+                span.duration = duration.map(Duration::microseconds);
+                span.annotations = annotations.unwrap_or_default();
+                span.binary_annotations = binary_annotations.unwrap_or_default();
+                span.debug = debug;
+
+                Ok(span)
+            }
+        }
+
+        deserializer.deserialize_map(SpanVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn microseconds() {
+        assert_eq!(from_microseconds(1_500_000_123_456).to_microseconds(),
+                   1_500_000_123_456);
+        assert_eq!(from_microseconds(-1).to_microseconds(), -1);
+    }
+
+    #[test]
+    fn value() {
+        assert_eq!(typed_value::<de::value::Error>(Value::I64(123), Some("I16")).unwrap(),
+                   Value::I16(123));
+        assert_eq!(typed_value::<de::value::Error>(Value::I64(123), Some("DOUBLE")).unwrap(),
+                   Value::Double(123.0));
+        assert_eq!(typed_value::<de::value::Error>(Value::Str("c29tZQ=="), Some("BYTES"))
+                       .unwrap(),
+                   Value::Blob(b"some".to_vec()));
+        assert_eq!(typed_value::<de::value::Error>(Value::Str("GET"), None).unwrap(),
+                   Value::Str("GET"));
+        assert!(typed_value::<de::value::Error>(Value::Str("GET"), Some("I32")).is_err());
+        assert!(typed_value::<de::value::Error>(Value::I64(32768), Some("I16")).is_err());
+        assert!(typed_value::<de::value::Error>(Value::I64(-2147483649), Some("I32")).is_err());
+        assert_eq!(typed_value::<de::value::Error>(Value::I64(-32768), Some("I16")).unwrap(),
+                   Value::I16(-32768));
+        assert!(typed_value::<de::value::Error>(Value::Str("!"), Some("BYTES")).is_err());
+    }
+}
@@ -1,7 +1,11 @@
-use std::mem;
+use std::fmt;
+use std::i64;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::cell::RefCell;
-use std::net::SocketAddr;
+use std::net::{SocketAddr, IpAddr, UdpSocket};
 
 use time;
 
@@ -9,6 +13,12 @@ use rand::{self, Rng};
 
 use xoroshiro128::{SeedableRng, Xoroshiro128Rng};
 
+use constants::{CLIENT_SEND, CLIENT_RECV, SERVER_SEND, SERVER_RECV, MESSAGE_SEND, MESSAGE_RECV,
+                WIRE_SEND, WIRE_RECV, CLIENT_ADDR, SERVER_ADDR, MESSAGE_ADDR};
+use errors::{Error, ErrorKind, Result};
+use sampler::Decision;
+use clock::{Clock, system_clock};
+
 /// Generate next id
 ///
 /// It base on the same workflow from `std::collections::RandomState`
@@ -34,8 +44,23 @@ pub fn next_id() -> u64 {
     SEEDS.with(|seeds| seeds.borrow_mut().next_u64())
 }
 
+fn invalid_id<T>(id: &str) -> Result<T> {
+    bail!(ErrorKind::InvalidId(id.to_owned()))
+}
+
+/// Parse the lower or upper hex id, which has 1 to 16 characters
+fn parse_hex_id(id: &str) -> Option<u64> {
+    if !id.is_empty() && id.len() <= 16 && id.bytes().all(|b| (b as char).is_digit(16)) {
+        u64::from_str_radix(id, 16).ok()
+    } else {
+        None
+    }
+}
+
 /// Unique identifier for a trace, set on all spans within it.
-#[derive(Clone, Debug)]
+///
+/// It is formatted as 16 or 32 lower hex characters, depends on whether it is a 128-bit id.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct TraceId {
     pub lo: u64,
     pub hi: Option<u64>,
@@ -50,8 +75,111 @@ impl TraceId {
     }
 }
 
+impl PartialOrd for TraceId {
+    fn partial_cmp(&self, other: &TraceId) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TraceId {
+    fn cmp(&self, other: &TraceId) -> Ordering {
+        (self.hi, self.lo).cmp(&(other.hi, other.lo))
+    }
+}
+
+impl fmt::LowerHex for TraceId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.hi {
+            Some(hi) => write!(f, "{:016x}{:016x}", hi, self.lo),
+            None => write!(f, "{:016x}", self.lo),
+        }
+    }
+}
+
+impl fmt::Display for TraceId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::LowerHex::fmt(self, f)
+    }
+}
+
+impl FromStr for TraceId {
+    type Err = Error;
+
+    /// Parse the 64-bit id from up to 16 hex characters, or the 128-bit id from up to 32,
+    /// the shorter ids are left padded with zeros, and the zero id is invalid.
+    fn from_str(id: &str) -> Result<Self> {
+        let trace_id = if id.len() <= 16 {
+            parse_hex_id(id).map(|lo| TraceId { lo: lo, hi: None })
+        } else if id.len() <= 32 && id.is_char_boundary(id.len() - 16) {
+            let (hi, lo) = id.split_at(id.len() - 16);
+
+            match (parse_hex_id(hi), parse_hex_id(lo)) {
+                (Some(hi), Some(lo)) => {
+                    Some(TraceId {
+                             lo: lo,
+                             hi: Some(hi),
+                         })
+                }
+                _ => None,
+            }
+        } else {
+            None
+        };
+
+        match trace_id {
+            Some(trace_id) if trace_id.lo != 0 || trace_id.hi.unwrap_or(0) != 0 => Ok(trace_id),
+            _ => invalid_id(id),
+        }
+    }
+}
+
 /// Unique 8-byte identifier of this span within a trace.
-pub type SpanId = u64;
+///
+/// It is formatted as 16 lower hex characters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SpanId(pub u64);
+
+impl SpanId {
+    pub fn gen() -> SpanId {
+        SpanId(next_id())
+    }
+}
+
+impl From<u64> for SpanId {
+    fn from(id: u64) -> Self {
+        SpanId(id)
+    }
+}
+
+impl From<SpanId> for u64 {
+    fn from(id: SpanId) -> Self {
+        id.0
+    }
+}
+
+impl fmt::LowerHex for SpanId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl fmt::Display for SpanId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::LowerHex::fmt(self, f)
+    }
+}
+
+impl FromStr for SpanId {
+    type Err = Error;
+
+    /// Parse the id from up to 16 hex characters, and the zero id is invalid.
+    fn from_str(id: &str) -> Result<Self> {
+        match parse_hex_id(id) {
+            Some(span_id) if span_id != 0 => Ok(SpanId(span_id)),
+            _ => invalid_id(id),
+        }
+    }
+}
 
 /// Epoch microseconds
 pub type Timestamp = time::Timespec;
@@ -79,31 +207,66 @@ impl ToMicrosecond for Timestamp {
 
 pub type Duration = time::Duration;
 
+impl ToMicrosecond for Duration {
+    fn to_microseconds(&self) -> i64 {
+        self.num_microseconds().unwrap_or(i64::MAX)
+    }
+}
+
 /// Indicates the network context of a service recording an annotation with two exceptions.
 #[derive(Clone, Debug)]
 pub struct Endpoint<'a> {
     /// Classifier of a source or destination in lowercase, such as "zipkin-server".
-    pub name: Option<&'a str>,
+    pub name: Option<Cow<'a, str>>,
     /// Endpoint address packed in the network endian
     pub addr: Option<SocketAddr>,
 }
 
+impl<'a> Endpoint<'a> {
+    /// The endpoint of the local service, with the auto-detected primary IP address.
+    pub fn local(name: Option<&'a str>, port: u16) -> Endpoint<'a> {
+        Endpoint {
+            name: name.map(Cow::Borrowed),
+            addr: local_ip().map(|ip| SocketAddr::new(ip, port)),
+        }
+    }
+}
+
+/// Detect the primary IP address of the host, prefer IPv4 to IPv6.
+///
+/// It connects an UDP socket to a public address, which doesn't send any packet,
+/// and the OS chooses the source address from the routing table.
+pub fn local_ip() -> Option<IpAddr> {
+    [("0.0.0.0:0", "8.8.8.8:53"), ("[::]:0", "[2001:4860:4860::8888]:53")]
+        .iter()
+        .flat_map(|&(local, remote)| {
+                      UdpSocket::bind(local)
+                          .and_then(|socket| {
+                                        socket.connect(remote)?;
+                                        socket.local_addr()
+                                    })
+                          .ok()
+                  })
+        .map(|addr| addr.ip())
+        .find(|ip| !ip.is_unspecified() && !ip.is_loopback())
+}
+
 /// Associates an event that explains latency with a timestamp.
 #[derive(Clone, Debug)]
 pub struct Annotation<'a> {
     /// Microseconds from epoch.
     pub timestamp: Timestamp,
     /// Usually a short tag indicating an event
-    pub value: &'a str,
+    pub value: Cow<'a, str>,
     /// The host that recorded, primarily for query by service name.
     pub endpoint: Option<Arc<Endpoint<'a>>>,
 }
 
 impl<'a> Annotation<'a> {
-    fn new(value: &'a str, endpoint: Option<Arc<Endpoint<'a>>>) -> Annotation<'a> {
+    fn new(clock: &Clock, value: &'a str, endpoint: Option<Arc<Endpoint<'a>>>) -> Annotation<'a> {
         Annotation {
-            value: value,
-            timestamp: now(),
+            value: value.into(),
+            timestamp: clock.now(),
             endpoint: endpoint,
         }
     }
@@ -113,6 +276,8 @@ impl<'a> Annotation<'a> {
 pub enum Value<'a> {
     Bool(bool),
     Bytes(&'a [u8]),
+    /// The owned bytes, like the decoded `BYTES` values
+    Blob(Vec<u8>),
     I16(i16),
     I32(i32),
     I64(i64),
@@ -130,11 +295,11 @@ impl<'a> Value<'a> {
         }
     }
 
-    pub fn as_bytes(&self) -> Option<&'a [u8]> {
-        if let &Value::Bytes(v) = self {
-            Some(v)
-        } else {
-            None
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            &Value::Bytes(v) => Some(v),
+            &Value::Blob(ref v) => Some(v.as_slice()),
+            _ => None,
         }
     }
 
@@ -299,6 +464,12 @@ impl<'a> From<&'a [u8]> for Value<'a> {
     }
 }
 
+impl<'a> From<Vec<u8>> for Value<'a> {
+    fn from(v: Vec<u8>) -> Self {
+        Value::Blob(v)
+    }
+}
+
 pub trait BinaryAnnotationValue<'a> {
     fn to_value(self) -> Value<'a>;
 }
@@ -314,7 +485,7 @@ impl<'a, T> BinaryAnnotationValue<'a> for T
 #[derive(Clone, Debug)]
 pub struct BinaryAnnotation<'a> {
     /// Name used to lookup spans
-    pub key: &'a str,
+    pub key: Cow<'a, str>,
     /// Value of annotation
     pub value: Value<'a>,
     /// The host that recorded, primarily for query by service name.
@@ -329,20 +500,80 @@ impl<'a> BinaryAnnotation<'a> {
         where V: Sized + BinaryAnnotationValue<'a>
     {
         BinaryAnnotation {
-            key: key,
+            key: key.into(),
             value: value.to_value(),
             endpoint: endpoint,
         }
     }
 }
 
+/// The role of the local service in a remote procedure call or messaging.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// The span describes the client side of a RPC.
+    Client,
+    /// The span describes the server side of a RPC.
+    Server,
+    /// The span describes a producer sending a message to a broker.
+    Producer,
+    /// The span describes a consumer receiving a message from a broker.
+    Consumer,
+}
+
+impl Kind {
+    /// The name used by the v2 model
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Kind::Client => "CLIENT",
+            Kind::Server => "SERVER",
+            Kind::Producer => "PRODUCER",
+            Kind::Consumer => "CONSUMER",
+        }
+    }
+
+    /// The core annotations at the start and finish of the span used by the v1 model.
+    ///
+    /// The producer finishes when the message was delivered to the wire ("ms", "ws"),
+    /// and the consumer starts when the message arrived from the wire ("wr", "mr").
+    pub fn annotations(&self) -> (&'static str, &'static str) {
+        match *self {
+            Kind::Client => (CLIENT_SEND, CLIENT_RECV),
+            Kind::Server => (SERVER_RECV, SERVER_SEND),
+            Kind::Producer => (MESSAGE_SEND, WIRE_SEND),
+            Kind::Consumer => (WIRE_RECV, MESSAGE_RECV),
+        }
+    }
+
+    /// The binary annotation key of the remote address used by the v1 model
+    pub fn address_key(&self) -> &'static str {
+        match *self {
+            Kind::Client => SERVER_ADDR,
+            Kind::Server => CLIENT_ADDR,
+            Kind::Producer | Kind::Consumer => MESSAGE_ADDR,
+        }
+    }
+}
+
+/// The propagated state of a span, usually extracted from the headers of an incoming request.
+#[derive(Clone, Debug)]
+pub struct SpanContext {
+    /// Unique identifier for a trace, set on all spans within it.
+    pub trace_id: TraceId,
+    /// The id of the upstream span, which will be the parent of the joined span.
+    pub span_id: SpanId,
+    /// The sampling decision made by the upstream, or None if it was deferred.
+    pub sampled: Option<bool>,
+    /// A request from the upstream to store the trace even if it overrides sampling policy.
+    pub debug: Option<bool>,
+}
+
 /// A trace is a series of spans (often RPC calls) which form a latency tree.
 #[derive(Clone, Debug)]
 pub struct Span<'a> {
     /// Unique identifier for a trace, set on all spans within it.
     pub trace_id: TraceId,
     /// Span name in lowercase, rpc method for example.
-    pub name: &'a str,
+    pub name: Cow<'a, str>,
     /// Unique 8-byte identifier of this span within a trace.
     pub id: SpanId,
     /// The parent's id or None if this the root span in a trace.
@@ -360,29 +591,94 @@ pub struct Span<'a> {
     pub debug: Option<bool>,
     /// Span was sampled base on the sampling policy.
     pub sampled: Option<bool>,
+    /// Span is recorded locally but not exported, only meaningful when it wasn't sampled.
+    record_only: bool,
+    /// The endpoint used by the annotations and binary annotations without an endpoint.
+    pub local_endpoint: Option<Arc<Endpoint<'a>>>,
+    /// The role of the local service, or None for a local span.
+    pub kind: Option<Kind>,
+    /// The peer of the RPC or the message broker.
+    pub remote_endpoint: Option<Arc<Endpoint<'a>>>,
+    /// The clock of the span and annotation timestamps, shared by the spans of a trace.
+    pub clock: Arc<Clock>,
 }
 
 impl<'a> Span<'a> {
+    /// Create a root span with the shared system clock, the `Tracer` anchors each trace instead.
     pub fn new(name: &'a str) -> Span<'a> {
+        let clock = system_clock();
+
         Span {
             trace_id: TraceId::gen(),
+            name: name.into(),
+            id: SpanId::gen(),
+            parent_id: None,
+            timestamp: clock.now(),
+            duration: None,
+            annotations: vec![],
+            binary_annotations: vec![],
+            debug: None,
+            sampled: None,
+            record_only: false,
+            local_endpoint: None,
+            kind: None,
+            remote_endpoint: None,
+            clock: clock,
+        }
+    }
+
+    /// Restore a span with the known ids and timestamp, like the decoded spans.
+    pub fn from_parts(trace_id: TraceId,
+                      id: SpanId,
+                      name: Cow<'a, str>,
+                      timestamp: Timestamp)
+                      -> Span<'a> {
+        Span {
+            trace_id: trace_id,
             name: name,
-            id: next_id(),
-            timestamp: now(),
-            ..unsafe { mem::zeroed() }
+            id: id,
+            parent_id: None,
+            timestamp: timestamp,
+            duration: None,
+            annotations: vec![],
+            binary_annotations: vec![],
+            debug: None,
+            sampled: None,
+            record_only: false,
+            local_endpoint: None,
+            kind: None,
+            remote_endpoint: None,
+            clock: system_clock(),
         }
     }
 
     pub fn child(&self, name: &'a str) -> Span<'a> {
         Span {
             trace_id: self.trace_id.clone(),
-            name: name,
-            id: next_id(),
+            name: name.into(),
+            id: SpanId::gen(),
             parent_id: Some(self.id),
-            timestamp: now(),
+            timestamp: self.clock.now(),
+            duration: None,
+            annotations: vec![],
+            binary_annotations: vec![],
             debug: self.debug,
             sampled: self.sampled,
-            ..unsafe { mem::zeroed() }
+            record_only: self.record_only,
+            local_endpoint: self.local_endpoint.clone(),
+            kind: None,
+            remote_endpoint: None,
+            clock: self.clock.clone(),
+        }
+    }
+
+    /// The context which should be propagated to the downstream services.
+    pub fn context(&self) -> SpanContext {
+        SpanContext {
+            trace_id: self.trace_id.clone(),
+            span_id: self.id,
+            sampled: self.sampled,
+            debug: self.debug,
         }
     }
 
@@ -417,6 +713,125 @@ impl<'a> Span<'a> {
             ..self
         }
     }
+
+    pub fn with_local_endpoint(self, endpoint: Arc<Endpoint<'a>>) -> Self {
+        Span {
+            local_endpoint: Some(endpoint),
+            ..self
+        }
+    }
+
+    /// Use the clock, and restart the span with it.
+    pub fn with_clock(self, clock: Arc<Clock>) -> Self {
+        Span {
+            timestamp: clock.now(),
+            clock: clock,
+            ..self
+        }
+    }
+
+    /// The current time of the span's clock.
+    pub fn now(&self) -> Timestamp {
+        self.clock.now()
+    }
+
+    pub fn with_kind(self, kind: Kind) -> Self {
+        Span {
+            kind: Some(kind),
+            ..self
+        }
+    }
+
+    /// Record the peer of the RPC or the message broker, with the role of the local service.
+    pub fn set_remote_endpoint(&mut self, kind: Kind, endpoint: Endpoint<'a>) {
+        self.kind = Some(kind);
+        self.remote_endpoint = Some(Arc::new(endpoint));
+    }
+
+    /// Add the core annotations of the span kind at the start and finish of the span,
+    /// unless they were recorded manually.
+    ///
+    /// The span without a duration only gets the start annotation, except the consumer span,
+    /// which usually has no duration and gets "mr" like the Zipkin's v2 to v1 conversion.
+    pub fn annotate_kind(&mut self) {
+        if let Some(kind) = self.kind {
+            let (start, finish) = kind.annotations();
+            let start = if kind == Kind::Consumer && self.duration.is_none() {
+                finish
+            } else {
+                start
+            };
+
+            if !self.annotations
+                    .iter()
+                    .any(|annotation| annotation.value == start) {
+                let annotation = Annotation {
+                    timestamp: self.timestamp,
+                    value: start.into(),
+                    endpoint: self.local_endpoint.clone(),
+                };
+
+                self.annotations.insert(0, annotation);
+            }
+
+            if let Some(duration) = self.duration {
+                if !self.annotations
+                        .iter()
+                        .any(|annotation| annotation.value == finish) {
+                    let annotation = Annotation {
+                        timestamp: self.timestamp + duration,
+                        value: finish.into(),
+                        endpoint: self.local_endpoint.clone(),
+                    };
+
+                    self.annotations.push(annotation);
+                }
+            }
+        }
+    }
+
+    /// The remote endpoint in the v1 model, a `CLIENT_ADDR`, `SERVER_ADDR` or `MESSAGE_ADDR`
+    /// binary annotation with a `true` value and the endpoint.
+    pub fn remote_address(&self) -> Option<BinaryAnnotation<'a>> {
+        match (self.kind, &self.remote_endpoint) {
+            (Some(kind), &Some(ref endpoint)) => {
+                Some(BinaryAnnotation::new(kind.address_key(), true, Some(endpoint.clone())))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn with_decision(self, decision: Decision) -> Self {
+        Span {
+            debug: if decision == Decision::Debug {
+                Some(true)
+            } else {
+                None
+            },
+            sampled: decision.sampled(),
+            record_only: decision == Decision::RecordOnly,
+            ..self
+        }
+    }
+
+    /// Span is recorded locally, by the processors, but not exported to the collector.
+    pub fn is_record_only(&self) -> bool {
+        self.decision() == Decision::RecordOnly
+    }
+
+    /// The sampling decision of the span.
+    pub fn decision(&self) -> Decision {
+        if self.debug == Some(true) {
+            Decision::Debug
+        } else {
+            match self.sampled {
+                Some(true) => Decision::Sample,
+                Some(false) if self.record_only => Decision::RecordOnly,
+                Some(false) => Decision::Drop,
+                None => Decision::Defer,
+            }
+        }
+    }
 }
 
 pub trait Annotatable<'a> {
@@ -430,16 +845,22 @@ pub trait Annotatable<'a> {
 
 impl<'a> Annotatable<'a> for Span<'a> {
     fn used(&self) -> bool {
-        self.debug == Some(true) || self.sampled != Some(false)
+        self.decision().is_recorded()
     }
 
     fn annotate(&mut self, value: &'a str, endpoint: Option<Arc<Endpoint<'a>>>) {
-        self.annotations.push(Annotation::new(value, endpoint))
+        let endpoint = endpoint.or_else(|| self.local_endpoint.clone());
+
+        let annotation = Annotation::new(&*self.clock, value, endpoint);
+
+        self.annotations.push(annotation)
     }
 
     fn binary_annotate<V>(&mut self, key: &'a str, value: V, endpoint: Option<Arc<Endpoint<'a>>>)
         where V: Sized + BinaryAnnotationValue<'a>
     {
+        let endpoint = endpoint.or_else(|| self.local_endpoint.clone());
+
         self.binary_annotations
             .push(BinaryAnnotation::new(key, value, endpoint))
     }
@@ -447,24 +868,20 @@ impl<'a> Annotatable<'a> for Span<'a> {
 
 impl<'a> Annotatable<'a> for Option<Span<'a>> {
     fn used(&self) -> bool {
-        if let Some(Span { debug, sampled, .. }) = *self {
-            debug == Some(true) || sampled != Some(false)
-        } else {
-            false
-        }
+        self.as_ref().map_or(false, |span| span.used())
     }
 
     fn annotate(&mut self, value: &'a str, endpoint: Option<Arc<Endpoint<'a>>>) {
-        if let Some(&mut Span { ref mut annotations, .. }) = self.as_mut() {
-            annotations.push(Annotation::new(value, endpoint))
+        if let Some(span) = self.as_mut() {
+            span.annotate(value, endpoint)
         }
     }
 
     fn binary_annotate<V>(&mut self, key: &'a str, value: V, endpoint: Option<Arc<Endpoint<'a>>>)
         where V: Sized + BinaryAnnotationValue<'a>
     {
-        if let Some(&mut Span { ref mut binary_annotations, .. }) = self.as_mut() {
-            binary_annotations.push(BinaryAnnotation::new(key, value, endpoint))
+        if let Some(span) = self.as_mut() {
+            span.binary_annotate(key, value, endpoint)
         }
     }
 }
@@ -490,6 +907,60 @@ mod tests {
         assert!(trace_id.hi.unwrap() != 0);
     }
 
+    #[test]
+    fn parse_id() {
+        let trace_id: TraceId = "463ac35c9f6413ad48485a3953bb6124".parse().unwrap();
+
+        assert_eq!(trace_id,
+                   TraceId {
+                       lo: 0x48485a3953bb6124,
+                       hi: Some(0x463ac35c9f6413ad),
+                   });
+        assert_eq!(trace_id.to_string(), "463ac35c9f6413ad48485a3953bb6124");
+        assert_eq!(format!("{:x}", trace_id), "463ac35c9f6413ad48485a3953bb6124");
+
+        let trace_id: TraceId = "48485A3953BB6124".parse().unwrap();
+
+        assert_eq!(trace_id,
+                   TraceId {
+                       lo: 0x48485a3953bb6124,
+                       hi: None,
+                   });
+        assert_eq!(trace_id.to_string(), "48485a3953bb6124");
+
+        // the shorter ids are left padded with zeros
+        assert_eq!("7b".parse::<TraceId>().unwrap().to_string(),
+                   "000000000000007b");
+        assert_eq!("1000000000000007b".parse::<TraceId>().unwrap().to_string(),
+                   "0000000000000001000000000000007b");
+
+        let span_id: SpanId = "53995c3f42cd8ad8".parse().unwrap();
+
+        assert_eq!(span_id, SpanId(0x53995c3f42cd8ad8));
+        assert_eq!(span_id.to_string(), "53995c3f42cd8ad8");
+        assert_eq!(SpanId(123).to_string(), "000000000000007b");
+
+        for id in &["", "0", "0000000000000000", "00000000000000000000000000000000",
+                    "463ac35c9f6413ad48485a3953bb61240", "463ac35c9f6413ag", "-1", "+1",
+                    "463ac35c 9f6413a"] {
+            assert!(id.parse::<TraceId>().is_err(), "{}", id);
+        }
+        for id in &["", "0", "53995c3f42cd8ad80", "53995c3f42cd8adg"] {
+            assert!(id.parse::<SpanId>().is_err(), "{}", id);
+        }
+
+        let mut ids = vec![TraceId { lo: 2, hi: Some(1) },
+                           TraceId { lo: 3, hi: None },
+                           TraceId { lo: 1, hi: Some(1) }];
+
+        ids.sort();
+
+        assert_eq!(ids,
+                   vec![TraceId { lo: 3, hi: None },
+                        TraceId { lo: 1, hi: Some(1) },
+                        TraceId { lo: 2, hi: Some(1) }]);
+    }
+
     #[test]
     fn span() {
         let span = Span::new("test");
@@ -500,7 +971,7 @@ mod tests {
 
         assert_eq!(span.name, "test");
 
-        assert!(span.id != 0);
+        assert!(span.id != SpanId(0));
         assert_eq!(span.parent_id, None);
         assert!(span.timestamp.to_microseconds() != 0);
         assert_eq!(span.duration, None);
@@ -508,16 +979,58 @@ mod tests {
         assert!(span.binary_annotations.is_empty());
         assert_eq!(span.debug, None);
 
-        assert_eq!(span.clone().with_id(123).id, 123);
-        assert_eq!(span.clone().with_parent_id(456).parent_id, Some(456));
+        assert_eq!(span.clone().with_id(SpanId(123)).id, SpanId(123));
+        assert_eq!(span.clone().with_parent_id(SpanId(456)).parent_id,
+                   Some(SpanId(456)));
         assert_eq!(span.clone().with_debug(true).debug, Some(true));
+
+        let child = span.clone().with_sampled(false).child("child");
+
+        assert_eq!(child.name, "child");
+        assert_eq!(child.trace_id.lo, span.trace_id.lo);
+        assert_eq!(child.parent_id, Some(span.id));
+        assert!(child.id != span.id);
+        assert_eq!(child.sampled, Some(false));
+
+        assert_eq!(child.decision(), Decision::Drop);
+
+        let context = child.context();
+
+        assert_eq!(context.trace_id.lo, span.trace_id.lo);
+        assert_eq!(context.span_id, child.id);
+        assert_eq!(context.sampled, Some(false));
+        assert_eq!(context.debug, None);
+    }
+
+    #[test]
+    fn decision() {
+        let span = Span::new("test");
+
+        assert_eq!(span.decision(), Decision::Defer);
+
+        for &decision in &[Decision::Drop,
+                           Decision::RecordOnly,
+                           Decision::Sample,
+                           Decision::Debug,
+                           Decision::Defer] {
+            let span = span.clone().with_decision(decision);
+
+            assert_eq!(span.decision(), decision);
+            assert_eq!(span.used(), decision.is_recorded());
+            assert_eq!(span.child("child").decision(), decision);
+        }
+
+        let span = span.with_decision(Decision::RecordOnly);
+
+        assert_eq!(span.sampled, Some(false));
+        assert_eq!(span.context().sampled, Some(false));
     }
 
     #[test]
     fn annonation() {
         let mut span = Span::new("test");
         let endpoint = Some(Arc::new(Endpoint {
-                                         name: Some("test"),
+                                         name: Some("test".into()),
                                          addr: None,
                                      }));
 
@@ -529,7 +1042,7 @@ mod tests {
             assert_eq!(annonation.value, CLIENT_SEND);
             assert!(annonation.timestamp.to_microseconds() != 0);
             assert!(annonation.endpoint.is_some());
-            assert_eq!(annonation.endpoint.as_ref().unwrap().name, Some("test"));
+            assert_eq!(annonation.endpoint.as_ref().unwrap().name, Some("test".into()));
         }
         span.annotate(CLIENT_RECV, None);
         {
@@ -638,7 +1151,7 @@ mod tests {
     fn macros() {
         let mut span = Span::new("test");
         let endpoint = Some(Arc::new(Endpoint {
-                                         name: Some("test"),
+                                         name: Some("test".into()),
                                          addr: None,
                                      }));
 
@@ -656,7 +1169,7 @@ mod tests {
 
             assert_eq!(span.annotations.len(), 2);
             assert_eq!(annonation.value, CLIENT_RECV);
-            assert_eq!(annonation.endpoint.as_ref().unwrap().name, Some("test"));
+            assert_eq!(annonation.endpoint.as_ref().unwrap().name, Some("test".into()));
         }
 
         annotate!(span, HTTP_METHOD, "GET");
@@ -675,7 +1188,7 @@ mod tests {
             assert_eq!(span.binary_annotations.len(), 2);
             assert_eq!(annonation.key, HTTP_STATUS_CODE);
             assert_eq!(annonation.value, Value::I16(123));
-            assert_eq!(annonation.endpoint.as_ref().unwrap().name, Some("test"));
+            assert_eq!(annonation.endpoint.as_ref().unwrap().name, Some("test".into()));
         }
 
         span = span.with_sampled(false);
@@ -697,14 +1210,120 @@ mod tests {
         annotate!(span, CLIENT_RECV_FRAGMENT);
         {
             assert_eq!(span.map(|span| {
-                                    (span.annotations.len(), span.annotations.last().unwrap().value)
+                                    (span.annotations.len(), span.annotations.last().unwrap().value.clone())
                                 })
                            .unwrap(),
-                       (3, CLIENT_RECV_FRAGMENT));
+                       (3, CLIENT_RECV_FRAGMENT.into()));
         }
 
         span = None;
 
         annotate!(span, CLIENT_RECV_FRAGMENT);
+
+        let mut span = Span::new("test").with_decision(Decision::RecordOnly);
+
+        annotate!(span, CLIENT_SEND);
+        {
+            assert_eq!(span.annotations.len(), 1);
+        }
+    }
+
+    #[test]
+    fn remote_endpoint() {
+        let mut span = Span::new("test");
+
+        assert!(span.remote_address().is_none());
+
+        span.set_remote_endpoint(Kind::Server,
+                                 Endpoint {
+                                     name: Some("client".into()),
+                                     addr: None,
+                                 });
+
+        assert_eq!(span.kind, Some(Kind::Server));
+
+        let address = span.remote_address().unwrap();
+
+        assert_eq!(address.key, CLIENT_ADDR);
+        assert_eq!(address.value, Value::Bool(true));
+        assert_eq!(address.endpoint.unwrap().name, Some("client".into()));
+
+        span = span.with_kind(Kind::Producer);
+
+        assert_eq!(span.remote_address().unwrap().key, MESSAGE_ADDR);
+        assert!(span.child("child").remote_endpoint.is_none());
+    }
+
+    #[test]
+    fn annotate_kind() {
+        let mut span = Span::new("test").with_kind(Kind::Client);
+
+        span.annotate(HTTP_METHOD, None);
+        span.annotate_kind();
+
+        assert_eq!(span.annotations.len(), 2);
+        assert_eq!(span.annotations[0].value, CLIENT_SEND);
+        assert_eq!(span.annotations[0].timestamp, span.timestamp);
+
+        span.duration = Some(Duration::milliseconds(10));
+        span.annotate_kind();
+
+        assert_eq!(span.annotations.len(), 3);
+        assert_eq!(span.annotations[2].value, CLIENT_RECV);
+        assert_eq!(span.annotations[2].timestamp,
+                   span.timestamp + Duration::milliseconds(10));
+
+        let mut span = Span::new("test").with_kind(Kind::Consumer);
+
+        span.duration = Some(Duration::zero());
+        span.annotate_kind();
+
+        assert_eq!(span.annotations
+                       .iter()
+                       .map(|annotation| &*annotation.value)
+                       .collect::<Vec<&str>>(),
+                   vec![WIRE_RECV, MESSAGE_RECV]);
+
+        for &(kind, expected) in &[(Kind::Consumer, MESSAGE_RECV), (Kind::Producer, MESSAGE_SEND)] {
+            let mut span = Span::new("test").with_kind(kind);
+
+            span.annotate_kind();
+
+            assert_eq!(span.annotations
+                           .iter()
+                           .map(|annotation| &*annotation.value)
+                           .collect::<Vec<&str>>(),
+                       vec![expected]);
+            assert_eq!(span.annotations[0].timestamp, span.timestamp);
+        }
+    }
+
+    #[test]
+    fn local_endpoint() {
+        let local = Arc::new(Endpoint::local(Some("local"), 8080));
+        let remote = Some(Arc::new(Endpoint {
+                                       name: Some("remote".into()),
+                                       addr: None,
+                                   }));
+
+        assert!(local.addr.map_or(true, |addr| addr.port() == 8080));
+
+        let span = Span::new("test").with_local_endpoint(local);
+        let mut child = span.child("child");
+
+        annotate!(child, CLIENT_SEND);
+        annotate!(child, CLIENT_RECV, endpoint => remote.clone());
+        annotate!(child, HTTP_METHOD, "GET");
+
+        assert_eq!(child.annotations[0].endpoint.as_ref().unwrap().name,
+                   Some("local".into()));
+        assert_eq!(child.annotations[1].endpoint.as_ref().unwrap().name,
+                   Some("remote".into()));
+        assert_eq!(child.binary_annotations[0]
+                       .endpoint
+                       .as_ref()
+                       .unwrap()
+                       .name,
+                   Some("local".into()));
     }
 }
\ No newline at end of file
@@ -0,0 +1,406 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use constants::{ERROR, HTTP_STATUS_CODE};
+use sampler::random;
+use span::{TraceId, Duration, Value, Span};
+use collector::Collector;
+
+/// The policy to decide whether a buffered trace should be kept
+#[derive(Clone, Debug, PartialEq)]
+pub enum Policy {
+    /// Keep the trace if any span has an `ERROR` annotation or binary annotation
+    Error,
+    /// Keep the trace if any span has a `HTTP_STATUS_CODE` greater or equal to the status code
+    HttpStatus(u16),
+    /// Keep the trace if the duration of its root span is above the threshold
+    Latency(Duration),
+    /// Keep the trace with the probability
+    Probability(f64),
+}
+
+impl Policy {
+    pub fn keep(&self, spans: &[Span]) -> bool {
+        match *self {
+            Policy::Error => {
+                spans
+                    .iter()
+                    .any(|span| {
+                        span.annotations
+                            .iter()
+                            .any(|annotation| annotation.value == ERROR) ||
+                        span.binary_annotations
+                            .iter()
+                            .any(|annotation| annotation.key == ERROR)
+                    })
+            }
+            Policy::HttpStatus(status_code) => {
+                spans
+                    .iter()
+                    .flat_map(|span| span.binary_annotations.iter())
+                    .filter(|annotation| annotation.key == HTTP_STATUS_CODE)
+                    .filter_map(|annotation| status_code_of(&annotation.value))
+                    .any(|code| code >= status_code)
+            }
+            Policy::Latency(threshold) => {
+                spans
+                    .iter()
+                    .filter(|span| span.parent_id.is_none())
+                    .filter_map(|span| span.duration)
+                    .any(|duration| duration > threshold)
+            }
+            Policy::Probability(probability) => random() < probability,
+        }
+    }
+}
+
+/// The HTTP status code of the value, or None if it is outside `100..600`
+fn status_code_of(value: &Value) -> Option<u16> {
+    let code = match *value {
+        Value::I16(v) => Some(v as i64),
+        Value::I32(v) => Some(v as i64),
+        Value::I64(v) => Some(v),
+        _ => value.as_str().and_then(|s| s.parse().ok()),
+    };
+
+    code.and_then(|code| if code >= 100 && code < 600 {
+                      Some(code as u16)
+                  } else {
+                      None
+                  })
+}
+
+struct Trace<'a> {
+    ts: Instant,
+    spans: Vec<Span<'a>>,
+}
+
+struct Buffer<'a> {
+    traces: HashMap<TraceId, Trace<'a>>,
+    order: VecDeque<TraceId>,
+    spans: usize,
+}
+
+impl<'a> Buffer<'a> {
+    fn push(&mut self, span: Span<'a>) {
+        let order = &mut self.order;
+
+        self.traces
+            .entry(span.trace_id.clone())
+            .or_insert_with(|| {
+                                order.push_back(span.trace_id.clone());
+
+                                Trace {
+                                    ts: Instant::now(),
+                                    spans: vec![],
+                                }
+                            })
+            .spans
+            .push(span);
+        self.spans += 1;
+    }
+
+    fn pop(&mut self) -> Option<Vec<Span<'a>>> {
+        self.order
+            .pop_front()
+            .and_then(|trace_id| self.traces.remove(&trace_id))
+            .map(|trace| {
+                     self.spans -= trace.spans.len();
+
+                     trace.spans
+                 })
+    }
+
+    fn expired(&self, window: Duration) -> bool {
+        self.order
+            .front()
+            .and_then(|trace_id| self.traces.get(trace_id))
+            .map_or(false,
+                    |trace| Duration::from_std(trace.ts.elapsed()).unwrap() >= window)
+    }
+}
+
+/// Tail based sampling
+///
+/// It buffers the finished spans by the trace id for a window,
+/// then decides whether the whole trace should be kept with the policies,
+/// and forwards the kept traces to the wrapped collector.
+///
+/// The spans which arrive after their trace was decided will be buffered as a new trace.
+pub struct TailSampling<'a, C: ?Sized> {
+    /// How long to wait for the spans of a trace
+    pub window: Duration,
+    /// The trace will be kept if any policy matched
+    pub policies: Vec<Policy>,
+    /// The maximum number of buffered traces, the oldest trace will be decided early when exceeds
+    pub max_traces: usize,
+    /// The maximum number of buffered spans, the oldest trace will be decided early when exceeds
+    pub max_spans: usize,
+    pub collector: Box<C>,
+    buffer: Mutex<Buffer<'a>>,
+}
+
+impl<'a, C: ?Sized> TailSampling<'a, C> {
+    pub fn new(window: Duration, policies: Vec<Policy>, collector: Box<C>) -> Self {
+        TailSampling {
+            window: window,
+            policies: policies,
+            max_traces: 10_000,
+            max_spans: 100_000,
+            collector: collector,
+            buffer: Mutex::new(Buffer {
+                                   traces: HashMap::new(),
+                                   order: VecDeque::new(),
+                                   spans: 0,
+                               }),
+        }
+    }
+
+    pub fn with_limits(self, max_traces: usize, max_spans: usize) -> Self {
+        TailSampling {
+            max_traces: max_traces,
+            max_spans: max_spans,
+            ..self
+        }
+    }
+
+    /// The number of buffered traces and spans
+    pub fn buffered(&self) -> (usize, usize) {
+        let buffer = self.buffer.lock().unwrap();
+
+        (buffer.traces.len(), buffer.spans)
+    }
+
+    fn keep(&self, spans: &[Span]) -> bool {
+        spans.iter().any(|span| span.debug == Some(true)) ||
+        self.policies.iter().any(|policy| policy.keep(spans))
+    }
+}
+
+impl<'a, C> TailSampling<'a, C>
+    where C: Collector<Item = Vec<Span<'a>>, Output = ()> + ?Sized
+{
+    /// Decide all the buffered traces, and forward the kept traces to the wrapped collector
+    pub fn flush(&self) -> Result<(), C::Error> {
+        let mut kept = vec![];
+        {
+            let mut buffer = self.buffer.lock().unwrap();
+
+            while let Some(spans) = buffer.pop() {
+                if self.keep(&spans) {
+                    kept.extend(spans);
+                }
+            }
+        }
+
+        self.forward(kept)
+    }
+
+    /// Decide the expired traces, and forward the kept traces to the wrapped collector
+    ///
+    /// The expired traces are decided when the new spans are submitted,
+    /// call it periodically, like from a timer, so a quiet service still exports its last traces.
+    pub fn expire(&self) -> Result<(), C::Error> {
+        self.submit(vec![])
+    }
+
+    fn forward(&self, spans: Vec<Span<'a>>) -> Result<(), C::Error> {
+        if spans.is_empty() {
+            Ok(())
+        } else {
+            self.collector.submit(spans)
+        }
+    }
+}
+
+impl<'a, C> Collector for TailSampling<'a, C>
+    where C: Collector<Item = Vec<Span<'a>>, Output = ()> + ?Sized
+{
+    type Item = Vec<Span<'a>>;
+    type Output = ();
+    type Error = C::Error;
+
+    fn submit(&self, spans: Self::Item) -> Result<Self::Output, Self::Error> {
+        let mut kept = vec![];
+        {
+            let mut buffer = self.buffer.lock().unwrap();
+
+            for span in spans {
+                buffer.push(span);
+            }
+
+            while buffer.expired(self.window) || buffer.traces.len() > self.max_traces ||
+                  buffer.spans > self.max_spans {
+                if let Some(spans) = buffer.pop() {
+                    if self.keep(&spans) {
+                        kept.extend(spans);
+                    }
+                }
+            }
+        }
+
+        self.forward(kept)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::marker::PhantomData;
+
+    use super::*;
+    use super::super::*;
+    use errors::*;
+
+    struct MockCollector<'a, T: 'a>(Mutex<Vec<String>>, PhantomData<&'a T>);
+
+    unsafe impl<'a, T> Sync for MockCollector<'a, T> {}
+    unsafe impl<'a, T> Send for MockCollector<'a, T> {}
+
+    impl<'a> Default for MockCollector<'a, Span<'a>> {
+        fn default() -> Self {
+            MockCollector(Mutex::new(vec![]), PhantomData)
+        }
+    }
+
+    impl<'a> Collector for MockCollector<'a, Span<'a>> {
+        type Item = Vec<Span<'a>>;
+        type Output = ();
+        type Error = Error;
+
+        fn submit(&self, spans: Vec<Span<'a>>) -> Result<()> {
+            self.0
+                .lock()
+                .unwrap()
+                .extend(spans.iter().map(|span| span.name.to_string()));
+
+            Ok(())
+        }
+    }
+
+    fn trace<'a>(name: &'a str) -> (Span<'a>, Span<'a>) {
+        let root = Span::new(name);
+        let child = root.child(name);
+
+        (root, child)
+    }
+
+    #[test]
+    fn policy() {
+        let (mut root, mut child) = trace("test");
+
+        assert!(!Policy::Error.keep(&[root.clone(), child.clone()]));
+        child.binary_annotate(ERROR, "timeout", None);
+        assert!(Policy::Error.keep(&[root.clone(), child.clone()]));
+
+        assert!(!Policy::HttpStatus(500).keep(&[root.clone()]));
+        root.binary_annotate(HTTP_STATUS_CODE, 404u16, None);
+        assert!(!Policy::HttpStatus(500).keep(&[root.clone()]));
+        root.binary_annotate(HTTP_STATUS_CODE, "503", None);
+        assert!(Policy::HttpStatus(500).keep(&[root.clone()]));
+
+        for &code in &[65736i64, -1, 99, 600] {
+            let (mut root, _) = trace("test");
+
+            root.binary_annotate(HTTP_STATUS_CODE, code, None);
+            assert!(!Policy::HttpStatus(200).keep(&[root]), "{}", code);
+        }
+
+        let threshold = Policy::Latency(Duration::milliseconds(100));
+
+        child.duration = Some(Duration::seconds(1));
+        assert!(!threshold.keep(&[root.clone(), child.clone()]));
+        root.duration = Some(Duration::seconds(1));
+        assert!(threshold.keep(&[root.clone(), child.clone()]));
+
+        assert!(Policy::Probability(1.0).keep(&[root.clone()]));
+        assert!(!Policy::Probability(0.0).keep(&[root.clone()]));
+    }
+
+    #[test]
+    fn buffer_trace() {
+        let collector = TailSampling::new(Duration::hours(1),
+                                          vec![Policy::Error],
+                                          Box::new(MockCollector::default()));
+
+        let (root, mut child) = trace("error");
+
+        child.binary_annotate(ERROR, "timeout", None);
+
+        collector.submit(vec![child]).unwrap();
+        assert_eq!(collector.buffered(), (1, 1));
+        collector.submit(vec![root]).unwrap();
+        assert_eq!(collector.buffered(), (1, 2));
+
+        let (root, child) = trace("ok");
+
+        collector.submit(vec![root, child]).unwrap();
+        assert_eq!(collector.buffered(), (2, 4));
+        assert!(collector.collector.0.lock().unwrap().is_empty());
+
+        collector.flush().unwrap();
+
+        assert_eq!(collector.buffered(), (0, 0));
+        assert_eq!(*collector.collector.0.lock().unwrap(), vec!["error", "error"]);
+    }
+
+    #[test]
+    fn expired_trace() {
+        let collector = TailSampling::new(Duration::zero(),
+                                          vec![Policy::Probability(1.0)],
+                                          Box::new(MockCollector::default()));
+
+        let (root, child) = trace("test");
+
+        collector.submit(vec![root, child]).unwrap();
+
+        assert_eq!(collector.buffered(), (0, 0));
+        assert_eq!(collector.collector.0.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn expire() {
+        let collector = TailSampling::new(Duration::milliseconds(10),
+                                          vec![Policy::Probability(1.0)],
+                                          Box::new(MockCollector::default()));
+
+        let (root, child) = trace("test");
+
+        collector.submit(vec![root, child]).unwrap();
+        collector.expire().unwrap();
+
+        assert_eq!(collector.buffered(), (1, 2));
+
+        ::std::thread::sleep(::std::time::Duration::from_millis(20));
+
+        collector.expire().unwrap();
+
+        assert_eq!(collector.buffered(), (0, 0));
+        assert_eq!(collector.collector.0.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn memory_limits() {
+        let collector = TailSampling::new(Duration::hours(1),
+                                          vec![Policy::Probability(1.0)],
+                                          Box::new(MockCollector::default()))
+                .with_limits(2, 3);
+
+        for name in &["first", "second"] {
+            let (root, child) = trace(name);
+
+            collector.submit(vec![root, child]).unwrap();
+        }
+
+        assert_eq!(collector.buffered(), (1, 2));
+        assert_eq!(*collector.collector.0.lock().unwrap(), vec!["first", "first"]);
+
+        for name in &["third", "fourth"] {
+            collector.submit(vec![Span::new(name)]).unwrap();
+        }
+
+        assert_eq!(collector.buffered(), (2, 2));
+        assert_eq!(collector.collector.0.lock().unwrap().len(), 4);
+    }
+}
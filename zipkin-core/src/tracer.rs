@@ -1,10 +1,24 @@
-use sampler::Sampler;
-use span::{Span, now};
+use std::cmp;
+use std::sync::Arc;
+
+use sampler::{Sampler, Decision};
+use span::{Endpoint, Span, SpanContext, Duration, Annotatable};
+use clock::{Clock, AnchoredClock};
+use id::IdGenerator;
+use processor::SpanProcessor;
 use collector::Collector;
 
 #[derive(Clone, Debug, Default)]
 pub struct Tracer<S, C: ?Sized> {
     pub sampler: Option<S>,
+    /// The default endpoint of the annotations recorded by the local service
+    pub local_endpoint: Option<Arc<Endpoint<'static>>>,
+    /// The clock of the started traces, or an anchored clock for each trace
+    pub clock: Option<Arc<Clock>>,
+    /// The generator of the trace and span ids, or the thread local random generator
+    pub id_generator: Option<Arc<IdGenerator>>,
+    /// The processors invoked in order when the recorded spans start and end
+    pub processors: Vec<Arc<SpanProcessor>>,
     pub collector: Box<C>,
 }
 
@@ -12,6 +26,10 @@ impl<'a, S, C: ?Sized> Tracer<S, C> {
     pub fn new(collector: Box<C>) -> Self {
         Tracer {
             sampler: None,
+            local_endpoint: None,
+            clock: None,
+            id_generator: None,
+            processors: vec![],
             collector: collector,
         }
     }
@@ -19,65 +37,214 @@ impl<'a, S, C: ?Sized> Tracer<S, C> {
     pub fn with_sampler(sampler: S, collector: Box<C>) -> Self {
         Tracer {
             sampler: Some(sampler),
+            local_endpoint: None,
+            clock: None,
+            id_generator: None,
+            processors: vec![],
             collector: collector,
         }
     }
+
+    pub fn with_local_endpoint(self, endpoint: Endpoint<'static>) -> Self {
+        Tracer {
+            local_endpoint: Some(Arc::new(endpoint)),
+            ..self
+        }
+    }
+
+    /// Use the service name with the auto-detected primary IP address as the local endpoint.
+    pub fn with_service_name(self, name: &'static str) -> Self {
+        self.with_local_endpoint(Endpoint::local(Some(name), 0))
+    }
+
+    pub fn with_clock(self, clock: Arc<Clock>) -> Self {
+        Tracer {
+            clock: Some(clock),
+            ..self
+        }
+    }
+
+    pub fn with_id_generator<G: IdGenerator + 'static>(self, id_generator: G) -> Self {
+        Tracer {
+            id_generator: Some(Arc::new(id_generator)),
+            ..self
+        }
+    }
+
+    pub fn with_processor<P: SpanProcessor + 'static>(mut self, processor: P) -> Self {
+        self.processors.push(Arc::new(processor));
+        self
+    }
+
+    /// Create a child span of the parent, the processors are invoked if it is recorded.
+    pub fn child(&self, parent: &Span<'a>, name: &'a str) -> Span<'a> {
+        self.started(self.generate(parent.child(name), false))
+    }
+
+    fn generate(&self, span: Span<'a>, root: bool) -> Span<'a> {
+        match self.id_generator {
+            Some(ref id_generator) => {
+                let span = if root {
+                    let trace_id = id_generator.trace_id_at(span.timestamp);
+
+                    span.with_trace_id(trace_id)
+                } else {
+                    span
+                };
+
+                span.with_id(id_generator.span_id())
+            }
+            None => span,
+        }
+    }
+
+    fn started(&self, mut span: Span<'a>) -> Span<'a> {
+        if span.used() {
+            for processor in &self.processors {
+                processor.on_start(&mut span);
+            }
+        }
+
+        span
+    }
 }
 
 impl<'a, S, C> Tracer<S, C>
     where S: Sampler<Item = Span<'a>>,
           C: ?Sized
 {
+    /// Start a new trace with a root span, the configured sampler decides whether it is sampled.
     pub fn span(&self, name: &'a str) -> Span<'a> {
-        let span = Span::new(name);
-        let sampled = self.sampler
-            .as_ref()
-            .map(|sampler| sampler.sample(&span));
+        self.start(Span::new(name))
+    }
+
+    /// Start a new trace with a prepared root span.
+    ///
+    /// The span may carry some initial binary annotations, like `HTTP_PATH`,
+    /// which could be used by the sampler to make the decision,
+    /// and its ids will be regenerated if the tracer has an id generator.
+    pub fn start(&self, span: Span<'a>) -> Span<'a> {
+        let span = self.generate(self.localize(span), true);
+        let decision = self.decide(&span);
+
+        self.started(span.with_decision(decision))
+    }
+
+    /// Join a trace started by the upstream caller.
+    ///
+    /// The `sampled` and `debug` flags extracted from the caller are always honored,
+    /// the configured sampler is only consulted when the caller deferred the decision.
+    pub fn join(&self, context: &SpanContext, name: &'a str) -> Span<'a> {
+        let span = self.localize(self.generate(Span::new(name), false)
+                                     .with_trace_id(context.trace_id.clone())
+                                     .with_parent_id(context.span_id));
+        let decision = match (context.debug, context.sampled) {
+            (Some(true), _) => Decision::Debug,
+            (_, Some(sampled)) => sampled.into(),
+            (_, None) => self.decide(&span),
+        };
 
-        Span {
-            sampled: sampled,
-            ..span
+        self.started(span.with_decision(decision))
+    }
+
+    fn localize(&self, span: Span<'a>) -> Span<'a> {
+        let span = match self.clock {
+            Some(ref clock) => span.with_clock(clock.clone()),
+            None => span.with_clock(Arc::new(AnchoredClock::new())),
+        };
+
+        match (&span.local_endpoint, &self.local_endpoint) {
+            (&None, &Some(ref endpoint)) => span.with_local_endpoint(endpoint.clone()),
+            _ => span,
         }
     }
+
+    fn decide(&self, span: &Span<'a>) -> Decision {
+        self.sampler
+            .as_ref()
+            .map_or(Decision::Defer, |sampler| sampler.decide(span))
+    }
 }
 
 impl<'a, S, C> Tracer<S, C>
     where C: Collector<Item = Vec<Span<'a>>> + ?Sized
 {
+    /// Finish the span and submit it to the collector,
+    /// unless the sampling decision or any processor drops it.
+    ///
+    /// The core annotations of the span kind are added, like `SERVER_RECV` and `SERVER_SEND`.
+    ///
+    /// The processors see all the recorded spans, include the record only ones.
+    ///
+    /// The collector receives an empty batch if the span wasn't exported.
     pub fn submit(&self,
-                  mut span: Span<'a>)
+                  span: Span<'a>)
                   -> Result<<C as Collector>::Output, <C as Collector>::Error> {
-        span.duration = Some(now() - span.timestamp);
+        self.collector.submit(self.process(span).into_iter().collect())
+    }
 
-        self.collector.submit(vec![span])
+    /// Finish the span like `submit`, and return `None` if it wasn't exported,
+    /// because it was dropped or only recorded locally.
+    pub fn finish(&self,
+                  span: Span<'a>)
+                  -> Result<Option<<C as Collector>::Output>, <C as Collector>::Error> {
+        match self.process(span) {
+            Some(span) => self.collector.submit(vec![span]).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn process(&self, mut span: Span<'a>) -> Option<Span<'a>> {
+        if !span.used() {
+            return None;
+        }
+
+        // durations of less than one microsecond must be rounded up to 1 microsecond
+        span.duration = Some(cmp::max(span.now() - span.timestamp, Duration::microseconds(1)));
+        span.annotate_kind();
+
+        for processor in &self.processors {
+            if !processor.on_end(&mut span) {
+                return None;
+            }
+        }
+
+        if span.decision().is_exported() {
+            Some(span)
+        } else {
+            None
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use std::marker::PhantomData;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     use super::super::*;
     use super::*;
     use errors::*;
 
-    struct MockCollector<'a, T: 'a>(PhantomData<&'a T>);
+    struct MockCollector<'a, T: 'a>(AtomicUsize, PhantomData<&'a T>);
 
     unsafe impl<'a, T> Sync for MockCollector<'a, T> {}
     unsafe impl<'a, T> Send for MockCollector<'a, T> {}
 
     impl<'a> Default for MockCollector<'a, Span<'a>> {
         fn default() -> Self {
-            MockCollector(PhantomData)
+            MockCollector(AtomicUsize::new(0), PhantomData)
         }
     }
 
     impl<'a> Collector for MockCollector<'a, Span<'a>> {
-        type Item = Span<'a>;
+        type Item = Vec<Span<'a>>;
         type Output = ();
         type Error = Error;
 
-        fn submit(&self, _: Span<'a>) -> Result<()> {
+        fn submit(&self, spans: Vec<Span<'a>>) -> Result<()> {
+            self.0.fetch_add(spans.len(), Ordering::Relaxed);
+
             Ok(())
         }
     }
@@ -95,4 +262,261 @@ mod tests {
 
         assert_eq!(tracer.span("test1").sampled, None);
     }
+
+    #[test]
+    fn join() {
+        let tracer = Tracer::with_sampler(FixedRate::new(2), Box::new(MockCollector::default()));
+        let mut context = SpanContext {
+            trace_id: TraceId {
+                lo: 123,
+                hi: Some(456),
+            },
+            span_id: SpanId(789),
+            sampled: Some(false),
+            debug: None,
+        };
+
+        let span = tracer.join(&context, "test1");
+
+        assert_eq!(span.trace_id.lo, 123);
+        assert_eq!(span.trace_id.hi, Some(456));
+        assert_eq!(span.parent_id, Some(SpanId(789)));
+        assert_eq!(span.sampled, Some(false));
+        assert_eq!(tracer.join(&context, "test2").sampled, Some(false));
+
+        context.debug = Some(true);
+
+        let span = tracer.join(&context, "test3");
+
+        assert_eq!(span.debug, Some(true));
+        assert_eq!(span.sampled, Some(true));
+
+        context.debug = None;
+        context.sampled = None;
+
+        assert_eq!(tracer.join(&context, "test4").sampled, Some(true));
+        assert_eq!(tracer.join(&context, "test5").sampled, Some(false));
+        assert_eq!(tracer.span("test6").sampled, Some(true));
+
+        let tracer = Tracer::<FixedRate<Span>, _>::new(Box::new(MockCollector::default()));
+
+        assert_eq!(tracer.join(&context, "test7").decision(), Decision::Defer);
+    }
+
+    #[test]
+    fn submit() {
+        let tracer = Tracer::with_sampler(FixedRate::new(2), Box::new(MockCollector::default()));
+
+        let span = tracer.span("test1");
+
+        assert_eq!(tracer.finish(span).unwrap(), Some(()));
+        assert_eq!(tracer.collector.0.load(Ordering::Relaxed), 1);
+
+        let span = tracer.span("test2");
+
+        assert_eq!(span.decision(), Decision::Drop);
+        assert_eq!(tracer.finish(span).unwrap(), None);
+
+        let span = tracer.span("test3").with_decision(Decision::RecordOnly);
+
+        assert!(span.is_record_only());
+        assert_eq!(tracer.finish(span).unwrap(), None);
+
+        let span = tracer.span("test4").with_decision(Decision::Debug);
+
+        tracer.submit(span).unwrap();
+        assert_eq!(tracer.collector.0.load(Ordering::Relaxed), 2);
+
+        let span = tracer.span("test5").with_decision(Decision::Drop);
+
+        tracer.submit(span).unwrap();
+        assert_eq!(tracer.collector.0.load(Ordering::Relaxed), 2);
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Submitted(usize);
+
+    struct CountingCollector<'a>(PhantomData<&'a ()>);
+
+    impl<'a> Collector for CountingCollector<'a> {
+        type Item = Vec<Span<'a>>;
+        type Output = Submitted;
+        type Error = Error;
+
+        fn submit(&self, spans: Vec<Span<'a>>) -> Result<Submitted> {
+            Ok(Submitted(spans.len()))
+        }
+    }
+
+    #[test]
+    fn submit_output() {
+        let tracer = Tracer::with_sampler(FixedRate::new(2),
+                                          Box::new(CountingCollector(PhantomData)));
+
+        assert_eq!(tracer.submit(tracer.span("test1")).unwrap(), Submitted(1));
+        assert_eq!(tracer.submit(tracer.span("test2")).unwrap(), Submitted(0));
+    }
+
+    #[test]
+    fn local_endpoint() {
+        let tracer = Tracer::with_sampler(FixedRate::new(1), Box::new(MockCollector::default()))
+            .with_service_name("test");
+
+        let mut span = tracer.span("test1");
+
+        annotate!(span, SERVER_RECV);
+
+        let endpoint = span.annotations[0].endpoint.as_ref().unwrap();
+
+        assert_eq!(endpoint.name, Some("test".into()));
+
+        let context = span.context();
+        let child = tracer.child(&tracer.join(&context, "test2"), "test3");
+
+        assert_eq!(child.local_endpoint.unwrap().name, Some("test".into()));
+    }
+
+    #[test]
+    fn id_generator() {
+        let tracer = Tracer::with_sampler(FixedRate::new(1), Box::new(MockCollector::default()))
+            .with_id_generator(SeededIdGenerator::new(123));
+        let ids = SeededIdGenerator::new(123);
+
+        let span = tracer.span("test");
+
+        assert_eq!(span.trace_id, ids.trace_id());
+        assert_eq!(span.id, ids.span_id());
+        assert_eq!(tracer.child(&span, "child").id, ids.span_id());
+        assert_eq!(tracer.join(&span.context(), "join").id, ids.span_id());
+
+        let tracer = Tracer::with_sampler(FixedRate::new(1), Box::new(MockCollector::default()))
+            .with_id_generator(TraceId64(RandomIdGenerator));
+
+        assert_eq!(tracer.span("test").trace_id.hi, None);
+
+        let trace_ids = (0..2)
+            .map(|_| {
+                Tracer::with_sampler(FixedRate::new(1), Box::new(MockCollector::default()))
+                    .with_clock(Arc::new(ManualClock::new(timestamp(1_500_000_000, 0))))
+                    .with_id_generator(EpochTraceId(SeededIdGenerator::new(123)))
+                    .span("test")
+                    .trace_id
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(trace_ids[0], trace_ids[1]);
+        assert_eq!(trace_ids[0].hi.unwrap() >> 32, 1_500_000_000);
+    }
+
+    struct ExpectDuration(Duration);
+
+    impl SpanProcessor for ExpectDuration {
+        fn on_end<'a>(&self, span: &mut Span<'a>) -> bool {
+            assert_eq!(span.duration, Some(self.0));
+
+            true
+        }
+    }
+
+    #[test]
+    fn clock() {
+        let clock = Arc::new(ManualClock::new(timestamp(123, 0)));
+        let tracer = Tracer::with_sampler(FixedRate::new(1), Box::new(MockCollector::default()))
+            .with_clock(clock.clone())
+            .with_processor(ExpectDuration(Duration::milliseconds(10)));
+
+        let span = tracer.span("test");
+
+        assert_eq!(span.timestamp, timestamp(123, 0));
+
+        clock.advance(Duration::milliseconds(10));
+
+        let mut child = tracer.child(&span, "child");
+
+        annotate!(child, "event");
+
+        assert_eq!(child.timestamp, timestamp(123, 10_000_000));
+        assert_eq!(child.annotations[0].timestamp, timestamp(123, 10_000_000));
+
+        clock.advance(Duration::milliseconds(10));
+
+        assert!(tracer.finish(child).unwrap().is_some());
+    }
+
+    struct ExpectAnnotations(Vec<&'static str>);
+
+    impl SpanProcessor for ExpectAnnotations {
+        fn on_end<'a>(&self, span: &mut Span<'a>) -> bool {
+            assert_eq!(span.annotations
+                           .iter()
+                           .map(|annotation| &*annotation.value)
+                           .collect::<Vec<&str>>(),
+                       self.0);
+
+            true
+        }
+    }
+
+    #[test]
+    fn kind() {
+        let tracer = Tracer::with_sampler(FixedRate::new(1), Box::new(MockCollector::default()))
+            .with_processor(ExpectAnnotations(vec![SERVER_RECV, "handled", SERVER_SEND]));
+
+        let mut span = tracer.start(Span::new("test").with_kind(Kind::Server));
+
+        annotate!(span, "handled");
+
+        assert!(tracer.finish(span).unwrap().is_some());
+    }
+
+    #[derive(Default)]
+    struct MockProcessor {
+        started: AtomicUsize,
+        ended: AtomicUsize,
+    }
+
+    impl SpanProcessor for Arc<MockProcessor> {
+        fn on_start<'a>(&self, span: &mut Span<'a>) {
+            self.started.fetch_add(1, Ordering::Relaxed);
+
+            span.annotate("started", None);
+        }
+
+        fn on_end<'a>(&self, span: &mut Span<'a>) -> bool {
+            self.ended.fetch_add(1, Ordering::Relaxed);
+
+            span.name != "drop"
+        }
+    }
+
+    #[test]
+    fn processors() {
+        let processor = Arc::new(MockProcessor::default());
+        let tracer = Tracer::with_sampler(FixedRate::new(2), Box::new(MockCollector::default()))
+            .with_processor(processor.clone())
+            .with_processor(DefaultTags::new().with("version", "1.0"));
+
+        let span = tracer.span("test1");
+
+        assert_eq!(span.annotations[0].value, "started");
+        assert_eq!(span.binary_annotations[0].key, "version");
+
+        let child = tracer.child(&span, "child");
+
+        assert_eq!(child.annotations.len(), 1);
+        assert_eq!(processor.started.load(Ordering::Relaxed), 2);
+
+        assert!(tracer.span("test2").annotations.is_empty());
+        assert_eq!(processor.started.load(Ordering::Relaxed), 2);
+
+        assert!(tracer.finish(span).unwrap().is_some());
+        assert!(tracer.finish(child).unwrap().is_some());
+        assert_eq!(tracer.finish(tracer.span("drop")).unwrap(), None);
+
+        let span = tracer.span("record").with_decision(Decision::RecordOnly);
+
+        assert_eq!(tracer.finish(span).unwrap(), None);
+        assert_eq!(processor.ended.load(Ordering::Relaxed), 4);
+        assert_eq!(tracer.collector.0.load(Ordering::Relaxed), 2);
+    }
 }
\ No newline at end of file
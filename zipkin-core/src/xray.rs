@@ -0,0 +1,224 @@
+use std::fmt;
+use std::str::FromStr;
+
+use errors::{Error, ErrorKind, Result};
+use span::{TraceId, SpanId, SpanContext};
+
+/// The header propagated by AWS X-Ray
+pub const X_AMZN_TRACE_ID: &'static str = "X-Amzn-Trace-Id";
+
+/// The `X-Amzn-Trace-Id` header
+///
+/// ```text
+/// X-Amzn-Trace-Id: Root=1-5759e988-bd862e3fe1be46a994272793;Parent=53995c3f42cd8ad8;Sampled=1
+/// ```
+///
+/// The `Root` is mapped to the 128-bit trace id, which high 32 bits are the epoch seconds,
+/// so the trace ids should be generated by `EpochTraceId`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AmznTraceId {
+    pub trace_id: TraceId,
+    /// The upstream span, absent if the request came from a load balancer which started the trace
+    pub parent_id: Option<SpanId>,
+    /// The sampling decision, or None if it was deferred
+    pub sampled: Option<bool>,
+}
+
+impl AmznTraceId {
+    /// The context to join the upstream span
+    pub fn context(&self) -> Option<SpanContext> {
+        self.parent_id
+            .map(|parent_id| {
+                     SpanContext {
+                         trace_id: self.trace_id.clone(),
+                         span_id: parent_id,
+                         sampled: self.sampled,
+                         debug: None,
+                     }
+                 })
+    }
+}
+
+impl<'a> From<&'a SpanContext> for AmznTraceId {
+    fn from(context: &'a SpanContext) -> Self {
+        AmznTraceId {
+            trace_id: context.trace_id.clone(),
+            parent_id: Some(context.span_id),
+            sampled: if context.debug == Some(true) {
+                Some(true)
+            } else {
+                context.sampled
+            },
+        }
+    }
+}
+
+/// The 64-bit trace id is rendered with a zero epoch, which is parsed back without the high bits.
+impl fmt::Display for AmznTraceId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let hi = self.trace_id.hi.unwrap_or(0);
+
+        write!(f,
+               "Root=1-{:08x}-{:08x}{:016x};",
+               hi >> 32,
+               hi & 0xFFFF_FFFF,
+               self.trace_id.lo)?;
+
+        if let Some(parent_id) = self.parent_id {
+            write!(f, "Parent={};", parent_id)?;
+        }
+
+        match self.sampled {
+            Some(true) => f.write_str("Sampled=1"),
+            Some(false) => f.write_str("Sampled=0"),
+            None => f.write_str("Sampled=?"),
+        }
+    }
+}
+
+fn invalid<T>(header: &str) -> Result<T> {
+    bail!(ErrorKind::InvalidHeader(X_AMZN_TRACE_ID, header.to_owned()))
+}
+
+fn parse_hex(s: &str, len: usize) -> Option<u64> {
+    if s.len() == len && s.bytes().all(|b| (b as char).is_digit(16)) {
+        u64::from_str_radix(s, 16).ok()
+    } else {
+        None
+    }
+}
+
+fn parse_root(root: &str) -> Option<TraceId> {
+    let mut parts = root.split('-');
+
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some("1"), Some(epoch), Some(id), None) if id.len() == 24 &&
+                                                     id.bytes().all(|b| b.is_ascii_hexdigit()) => {
+            match (parse_hex(epoch, 8), parse_hex(&id[..8], 8), parse_hex(&id[8..], 16)) {
+                (Some(epoch), Some(hi), Some(lo)) => {
+                    let hi = epoch << 32 | hi;
+
+                    Some(TraceId {
+                             lo: lo,
+                             hi: if hi == 0 { None } else { Some(hi) },
+                         })
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+impl FromStr for AmznTraceId {
+    type Err = Error;
+
+    fn from_str(header: &str) -> Result<Self> {
+        let mut trace_id = None;
+        let mut parent_id = None;
+        let mut sampled = None;
+
+        for field in header.split(';').map(|field| field.trim()) {
+            let mut kv = field.splitn(2, '=');
+
+            match (kv.next(), kv.next()) {
+                (Some("Root"), Some(root)) => {
+                    match parse_root(root) {
+                        Some(id) => trace_id = Some(id),
+                        None => return invalid(header),
+                    }
+                }
+                (Some("Parent"), Some(parent)) => {
+                    match parse_hex(parent, 16) {
+                        Some(id) => parent_id = Some(SpanId(id)),
+                        None => return invalid(header),
+                    }
+                }
+                (Some("Sampled"), Some("1")) => sampled = Some(true),
+                (Some("Sampled"), Some("0")) => sampled = Some(false),
+                (Some("Sampled"), Some("?")) => sampled = None,
+                (Some("Sampled"), _) => return invalid(header),
+                // the other fields, like `Self`, are ignored
+                _ => {}
+            }
+        }
+
+        match trace_id {
+            Some(trace_id) => {
+                Ok(AmznTraceId {
+                       trace_id: trace_id,
+                       parent_id: parent_id,
+                       sampled: sampled,
+                   })
+            }
+            None => invalid(header),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let header = "Root=1-5759e988-bd862e3fe1be46a994272793;Parent=53995c3f42cd8ad8;Sampled=1";
+        let id: AmznTraceId = header.parse().unwrap();
+
+        assert_eq!(id.trace_id,
+                   TraceId {
+                       lo: 0xe1be46a994272793,
+                       hi: Some(0x5759e988bd862e3f),
+                   });
+        assert_eq!(id.parent_id, Some(SpanId(0x53995c3f42cd8ad8)));
+        assert_eq!(id.sampled, Some(true));
+        assert_eq!(id.to_string(), header);
+
+        let context = id.context().unwrap();
+
+        assert_eq!(context.span_id, SpanId(0x53995c3f42cd8ad8));
+        assert_eq!(AmznTraceId::from(&context), id);
+
+        let id: AmznTraceId = "Root=1-5759e988-bd862e3fe1be46a994272793; Self=1-abc; Sampled=?"
+            .parse()
+            .unwrap();
+
+        assert_eq!(id.parent_id, None);
+        assert_eq!(id.sampled, None);
+        assert!(id.context().is_none());
+        assert_eq!(id.to_string(),
+                   "Root=1-5759e988-bd862e3fe1be46a994272793;Sampled=?");
+    }
+
+    #[test]
+    fn invalid_header() {
+        for header in &["",
+                        "Parent=53995c3f42cd8ad8",
+                        "Root=2-5759e988-bd862e3fe1be46a994272793",
+                        "Root=1-5759e988-bd862e3fe1be46a99427279",
+                        "Root=1-5759e98g-bd862e3fe1be46a994272793",
+                        "Root=1-5759e988-bd862e3ée1be46a99427279",
+                        "Root=1-5759e988-bd862e3fe1be46a9942727é",
+                        "Root=1-5759e988-bd862e3fe1be46a994272793;Parent=123",
+                        "Root=1-5759e988-bd862e3fe1be46a994272793;Sampled=yes"] {
+            assert!(header.parse::<AmznTraceId>().is_err(), "{}", header);
+        }
+    }
+
+    #[test]
+    fn without_epoch() {
+        let id = AmznTraceId {
+            trace_id: TraceId {
+                lo: 0xe1be46a994272793,
+                hi: None,
+            },
+            parent_id: Some(SpanId(0x53995c3f42cd8ad8)),
+            sampled: Some(false),
+        };
+
+        let header = "Root=1-00000000-00000000e1be46a994272793;Parent=53995c3f42cd8ad8;Sampled=0";
+
+        assert_eq!(id.to_string(), header);
+        assert_eq!(header.parse::<AmznTraceId>().unwrap(), id);
+    }
+}
@@ -0,0 +1,339 @@
+use std::io;
+use std::str;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::net::SocketAddr;
+use std::collections::VecDeque;
+
+use futures::{future, Future, Poll, Async};
+use futures::sync::oneshot;
+
+use tokio_io::AsyncRead;
+use tokio_io::io::write_all;
+use tokio_tcp::TcpStream;
+
+use httparse::{self, Status};
+
+pub type IoFuture<T> = Box<Future<Item = T, Error = io::Error> + Send>;
+
+/// The encoded request, shared with the retry on a fresh connection
+#[derive(Clone)]
+struct Request(Arc<Vec<u8>>);
+
+impl AsRef<[u8]> for Request {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// The non-blocking keep-alive connections to the collector,
+/// at most `max_open` of them, so at most `max_open` requests are in flight at the same time.
+///
+/// The sockets are driven by the default tokio reactor, a pending request holds no thread.
+pub struct Pool {
+    addr: SocketAddr,
+    max_open: usize,
+    state: Mutex<State>,
+}
+
+struct State {
+    open: usize,
+    idle: Vec<TcpStream>,
+    /// The requests waiting for a connection, `None` to open a new one
+    waiters: VecDeque<oneshot::Sender<Option<TcpStream>>>,
+}
+
+enum Checkout {
+    Idle(TcpStream),
+    Connect,
+    Wait(oneshot::Receiver<Option<TcpStream>>),
+}
+
+impl Pool {
+    pub fn new(addr: SocketAddr, max_open: usize) -> Self {
+        Pool {
+            addr: addr,
+            max_open: ::std::cmp::max(max_open, 1),
+            state: Mutex::new(State {
+                                  open: 0,
+                                  idle: vec![],
+                                  waiters: VecDeque::new(),
+                              }),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<State> {
+        // the state is consistent between the statements, even if a holder panicked
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn checkout(&self) -> Checkout {
+        let mut state = self.state();
+
+        if let Some(conn) = state.idle.pop() {
+            Checkout::Idle(conn)
+        } else if state.open < self.max_open {
+            state.open += 1;
+
+            Checkout::Connect
+        } else {
+            let (sender, receiver) = oneshot::channel();
+
+            state.waiters.push_back(sender);
+
+            Checkout::Wait(receiver)
+        }
+    }
+
+    /// Return a reusable connection, or the slot of a closed one, to the next waiting request.
+    fn checkin(&self, mut conn: Option<TcpStream>) {
+        let mut state = self.state();
+
+        while let Some(waiter) = state.waiters.pop_front() {
+            match waiter.send(conn) {
+                Ok(()) => return,
+                Err(returned) => conn = returned,
+            }
+        }
+
+        match conn {
+            Some(conn) => state.idle.push(conn),
+            None => state.open -= 1,
+        }
+    }
+
+    /// Send the request and wait for the status of the response,
+    /// retrying once on a fresh connection if the server has closed an idle one.
+    pub fn send(pool: &Arc<Pool>, request: Vec<u8>) -> IoFuture<u16> {
+        let request = Request(Arc::new(request));
+        let addr = pool.addr;
+        let conn: IoFuture<Option<TcpStream>> = match pool.checkout() {
+            Checkout::Idle(conn) => Box::new(future::ok(Some(conn))),
+            Checkout::Connect => Box::new(future::ok(None)),
+            Checkout::Wait(receiver) => {
+                Box::new(receiver.map_err(|_| {
+                                              io::Error::new(io::ErrorKind::Other,
+                                                             "connection pool dropped")
+                                          }))
+            }
+        };
+        let pool = pool.clone();
+
+        Box::new(conn.and_then(move |conn| -> IoFuture<_> {
+                match conn {
+                    Some(conn) => {
+                        Box::new(exchange(conn, request.clone()).or_else(move |err| {
+                            if is_stale(&err) {
+                                connect(&addr, request)
+                            } else {
+                                Box::new(future::err(err))
+                            }
+                        }))
+                    }
+                    None => connect(&addr, request),
+                }
+            })
+            .then(move |res| match res {
+                      Ok((conn, status)) => {
+                          pool.checkin(conn);
+
+                          Ok(status)
+                      }
+                      Err(err) => {
+                          pool.checkin(None);
+
+                          Err(err)
+                      }
+                  }))
+    }
+}
+
+fn connect(addr: &SocketAddr, request: Request) -> IoFuture<(Option<TcpStream>, u16)> {
+    Box::new(TcpStream::connect(addr).and_then(move |conn| exchange(conn, request)))
+}
+
+/// The idle connection was closed by the server before the request
+fn is_stale(err: &io::Error) -> bool {
+    match err.kind() {
+        io::ErrorKind::ConnectionAborted |
+        io::ErrorKind::ConnectionReset |
+        io::ErrorKind::BrokenPipe => true,
+        _ => false,
+    }
+}
+
+fn exchange(conn: TcpStream, request: Request) -> IoFuture<(Option<TcpStream>, u16)> {
+    Box::new(write_all(conn, request).and_then(|(conn, _)| {
+                                                   ReadResponse {
+                                                       conn: Some(conn),
+                                                       buf: Vec::with_capacity(1024),
+                                                   }
+                                               }))
+}
+
+/// Read the whole response, so the connection could be reused by the following requests.
+struct ReadResponse {
+    conn: Option<TcpStream>,
+    buf: Vec<u8>,
+}
+
+impl Future for ReadResponse {
+    type Item = (Option<TcpStream>, u16);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            let len = self.buf.len();
+
+            self.buf.resize(len + 4096, 0);
+
+            let read = self.conn
+                .as_mut()
+                .expect("poll a finished response")
+                .poll_read(&mut self.buf[len..]);
+
+            let read = match read {
+                Ok(Async::Ready(read)) => read,
+                Ok(Async::NotReady) => {
+                    self.buf.truncate(len);
+
+                    return Ok(Async::NotReady);
+                }
+                Err(err) => return Err(err),
+            };
+
+            self.buf.truncate(len + read);
+
+            match parse(&self.buf, read == 0)? {
+                Some((status, keep_alive)) => {
+                    let conn = self.conn.take();
+
+                    return Ok(Async::Ready((if keep_alive { conn } else { None }, status)));
+                }
+                None if read == 0 => {
+                    let kind = if self.buf.is_empty() {
+                        io::ErrorKind::ConnectionAborted
+                    } else {
+                        io::ErrorKind::UnexpectedEof
+                    };
+
+                    return Err(io::Error::new(kind, "connection closed before the response"));
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+fn invalid<E: ToString>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+/// The status and whether the connection could be reused, once the whole response is in `buf`.
+fn parse(buf: &[u8], eof: bool) -> io::Result<Option<(u16, bool)>> {
+    let mut headers = [httparse::EMPTY_HEADER; 64];
+    let mut res = httparse::Response::new(&mut headers);
+    let head = match res.parse(buf).map_err(invalid)? {
+        Status::Complete(head) => head,
+        Status::Partial => return Ok(None),
+    };
+    let status = res.code.unwrap_or_default();
+
+    if status >= 100 && status < 200 {
+        return parse(&buf[head..], eof);
+    }
+
+    let mut keep_alive = res.version == Some(1);
+    let mut length = None;
+    let mut chunked = false;
+
+    for header in res.headers.iter() {
+        let value = str::from_utf8(header.value)
+            .map_err(invalid)?
+            .trim()
+            .to_lowercase();
+
+        if header.name.eq_ignore_ascii_case("content-length") {
+            length = Some(value.parse::<usize>().map_err(invalid)?);
+        } else if header.name.eq_ignore_ascii_case("transfer-encoding") {
+            chunked = value.ends_with("chunked");
+        } else if header.name.eq_ignore_ascii_case("connection") {
+            if value.contains("close") {
+                keep_alive = false;
+            } else if value.contains("keep-alive") {
+                keep_alive = true;
+            }
+        }
+    }
+
+    let body = &buf[head..];
+    let complete = if status == 204 || status == 304 {
+        true
+    } else if chunked {
+        chunked_complete(body)?
+    } else if let Some(length) = length {
+        body.len() >= length
+    } else {
+        // the body is delimited by closing the connection
+        keep_alive = false;
+
+        eof
+    };
+
+    Ok(if complete {
+           Some((status, keep_alive))
+       } else {
+           None
+       })
+}
+
+fn chunked_complete(body: &[u8]) -> io::Result<bool> {
+    let mut pos = 0;
+
+    while pos < body.len() {
+        match httparse::parse_chunk_size(&body[pos..])
+                  .map_err(|_| invalid("invalid chunk size"))? {
+            Status::Partial => return Ok(false),
+            Status::Complete((size_line, 0)) => {
+                // the last chunk, and the trailers ends with an empty line
+                let rest = &body[pos + size_line..];
+
+                return Ok(rest.starts_with(b"\r\n") ||
+                          rest.windows(4).any(|w| w == b"\r\n\r\n"));
+            }
+            Status::Complete((size_line, size)) => pos += size_line + size as usize + 2,
+        }
+    }
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+
+    #[test]
+    fn response() {
+        for &(res, eof, parsed) in
+            &[(&b"HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\n\r\n"[..], false,
+               Some((202, true))),
+              (b"HTTP/1.1 202 Accepted\r\nContent-Length: 5\r\n\r\nab", false, None),
+              (b"HTTP/1.1 400 Bad Request\r\nContent-Length: 2\r\nConnection: close\r\n\r\nno",
+               false, Some((400, false))),
+              (b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 202 Accepted\r\nContent-Length: 0\r\n\r\n",
+               false, Some((202, true))),
+              (b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nok\r\n", false, None),
+              (b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nok\r\n0\r\n\r\n",
+               false, Some((200, true))),
+              (b"HTTP/1.0 200 OK\r\n\r\nbody", false, None),
+              (b"HTTP/1.0 200 OK\r\n\r\nbody", true, Some((200, false))),
+              (b"HTTP/1.1 204 No Content\r\n\r\n", false, Some((204, true))),
+              (b"HTTP/1.1 20", false, None)] {
+            assert_eq!(parse(res, eof).unwrap(),
+                       parsed,
+                       "{}",
+                       String::from_utf8_lossy(res));
+        }
+
+        assert!(parse(b"HTTP/1.1 200 OK\r\nContent-Length: x\r\n\r\n", false).is_err());
+    }
+}
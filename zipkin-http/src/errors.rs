@@ -6,5 +6,22 @@ error_chain! {
     }
     errors {
         ResponseError(status: ::hyper::status::StatusCode)
+        UnsupportedContentType(content_type: ::hyper::mime::Mime,
+                               version: ::zipkin_core::ApiVersion) {
+            description("unsupported content type")
+            display("unsupported content type `{}` for the {:?} API", content_type, version)
+        }
+        MismatchedPath(path: String, version: ::zipkin_core::ApiVersion) {
+            description("mismatched API path")
+            display("path `{}` mismatched the {:?} API", path, version)
+        }
+        UnsupportedScheme(scheme: String) {
+            description("unsupported scheme")
+            display("unsupported scheme `{}`", scheme)
+        }
+        UnresolvedHost(url: String) {
+            description("unresolved host")
+            display("unresolved host of `{}`", url)
+        }
     }
-}
\ No newline at end of file
+}
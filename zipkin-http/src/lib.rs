@@ -1,13 +1,19 @@
 #[macro_use]
 extern crate error_chain;
 extern crate hyper;
+extern crate futures;
+extern crate tokio_io;
+extern crate tokio_tcp;
+extern crate httparse;
 
 extern crate zipkin_core;
+extern crate zipkin_async;
 
 pub use hyper::client::RedirectPolicy;
 pub use hyper::Error as HttpError;
 
 pub mod errors;
+mod connection;
 mod transport;
 
-pub use transport::{HttpConfig, HttpTransport};
+pub use transport::{HttpConfig, HttpTransport, AsyncHttpTransport};
@@ -1,93 +1,481 @@
+use std::io;
+use std::sync::Arc;
 use std::time::Duration;
 use std::marker::PhantomData;
+use std::net::ToSocketAddrs;
 
 use hyper;
-use hyper::mime::Mime;
+use hyper::mime::{Mime, TopLevel, SubLevel};
 use hyper::client::{pool, Client, RedirectPolicy};
-use hyper::header::{Headers, ContentType};
+use hyper::header::{self, Headers, ContentType};
+use hyper::status::StatusCode;
 
-use zipkin_core::Transport;
+use futures::Future;
+
+use zipkin_core::{Transport, MimeType, ContentEncoding, ApiVersion};
+use zipkin_async::AsyncTransport;
 
 use errors::{Error, ErrorKind, Result};
+use connection::Pool;
 
 pub struct HttpConfig {
     pub content_type: Mime,
+    /// The content coding of the compressed messages
+    pub content_encoding: Option<ContentEncoding>,
+    /// The data model of the encoded spans, which decides the API path
+    pub api_version: ApiVersion,
     pub redirect_policy: RedirectPolicy,
     pub read_timeout: Option<Duration>,
     pub write_timeout: Option<Duration>,
     pub max_message_size: usize,
     pub max_idle_connections: Option<usize>,
+    /// The maximum number of the batches sent at the same time by `AsyncHttpTransport`
+    pub max_in_flight: usize,
 }
 
 impl HttpConfig {
     pub fn new(content_type: Mime) -> Self {
         HttpConfig {
             content_type: content_type,
+            content_encoding: None,
+            api_version: ApiVersion::V1,
             redirect_policy: RedirectPolicy::FollowAll,
             read_timeout: Some(Duration::from_secs(15)),
             write_timeout: Some(Duration::from_secs(15)),
             max_message_size: 4096,
             max_idle_connections: None,
+            max_in_flight: 4,
+        }
+    }
+
+    /// Derive the content type, content coding and API version from the codec
+    pub fn from_codec<C: MimeType + ?Sized>(codec: &C) -> Self {
+        HttpConfig {
+            content_encoding: codec.content_encoding(),
+            api_version: codec.api_version(),
+            ..HttpConfig::new(codec.mime_type())
         }
     }
 
+    /// Check the Zipkin collector accepts the content type with the API version,
+    /// JSON for both versions, and `TBinaryProtocol` Thrift for the v1 API only.
+    ///
+    /// It is only enforced for the `/api/v1/spans` and `/api/v2/spans` paths,
+    /// any content type may be posted to the other endpoints.
+    pub fn validate(&self) -> Result<()> {
+        let accepted = match (&self.content_type, self.api_version) {
+            (&Mime(TopLevel::Application, SubLevel::Json, _), _) => true,
+            (&Mime(TopLevel::Application, SubLevel::Ext(ref ext), _), ApiVersion::V1) => {
+                ext == "x-thrift"
+            }
+            _ => false,
+        };
+
+        if accepted {
+            Ok(())
+        } else {
+            bail!(ErrorKind::UnsupportedContentType(self.content_type.clone(), self.api_version))
+        }
+    }
+
+    /// The client with a pool of the keep-alive connections
+    pub fn client(&self) -> Client {
+        let mut client = self.max_idle_connections
+            .map_or_else(Client::new,
+                         |max_idle| Client::with_pool_config(pool::Config { max_idle: max_idle }));
+
+        client.set_redirect_policy(self.redirect_policy);
+        client.set_read_timeout(self.read_timeout);
+        client.set_write_timeout(self.write_timeout);
+
+        client
+    }
+
     pub fn headers(&self) -> Headers {
         let mut headers = Headers::new();
 
         headers.set(ContentType(self.content_type.clone()));
 
+        if let Some(encoding) = self.content_encoding {
+            let encoding = match encoding {
+                ContentEncoding::Gzip => header::Encoding::Gzip,
+                ContentEncoding::Deflate => header::Encoding::Deflate,
+            };
+
+            headers.set(header::ContentEncoding(vec![encoding]));
+        }
+
         headers
     }
 }
 
+/// The URL of the collector, the spans path of the API version is used if `base` has no path.
+fn spans_url(base: &str, config: &HttpConfig) -> Result<hyper::Url> {
+    let mut url = hyper::Url::parse(base)?;
+    let path = config.api_version.spans_path();
+
+    match url.path() {
+        "" | "/" => url.set_path(path),
+        p if p.starts_with("/api/v") && p != path => {
+            bail!(ErrorKind::MismatchedPath(p.to_owned(), config.api_version))
+        }
+        _ => {}
+    }
+
+    if url.path() == path {
+        config.validate()?;
+    }
+
+    Ok(url)
+}
+
+/// The persistent client and the resolved URL of the collector
+#[derive(Clone)]
+struct Sender {
+    url: hyper::Url,
+    config: Arc<HttpConfig>,
+    client: Arc<Client>,
+}
+
+impl Sender {
+    fn new(base: &str, config: HttpConfig) -> Result<Self> {
+        Ok(Sender {
+               url: spans_url(base, &config)?,
+               client: Arc::new(config.client()),
+               config: Arc::new(config),
+           })
+    }
+
+    fn post<E>(&self, body: &[u8]) -> ::std::result::Result<(), E>
+        where E: From<::hyper::Error> + From<Error>
+    {
+        let mut res = self.client
+            .post(self.url.clone())
+            .body(body)
+            .headers(self.config.headers())
+            .send()?;
+
+        // drain the response, so the connection is kept alive for the following batches
+        io::copy(&mut res, &mut io::sink()).map_err(Error::from)?;
+
+        if !res.status.is_success() {
+            let err: Error = ErrorKind::ResponseError(res.status).into();
+
+            bail!(err)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Send the spans to the collector, reusing the keep-alive connections across the batches.
 pub struct HttpTransport<B, E> {
-    base: hyper::Url,
-    config: HttpConfig,
-    phantom: PhantomData<(B, E)>,
+    sender: Sender,
+    phantom: PhantomData<fn(B) -> E>,
 }
 
 impl<B, E> HttpTransport<B, E> {
+    /// Send the spans to the collector at `base`,
+    /// the spans path of the API version is used if `base` has no path.
     pub fn new(base: &str, config: HttpConfig) -> Result<Self> {
         Ok(HttpTransport {
-               base: hyper::Url::parse(base)?,
-               config: config,
+               sender: Sender::new(base, config)?,
                phantom: PhantomData,
            })
     }
 }
 
 impl<B, E> Transport for HttpTransport<B, E>
-    where B: 'static + AsRef<[u8]> + Sync + Send,
-          E: 'static + From<::hyper::Error> + From<Error> + Sync + Send
+    where B: 'static + AsRef<[u8]> + Send,
+          E: 'static + From<::hyper::Error> + From<Error> + Send
 {
     type Buffer = B;
     type Output = ();
     type Error = E;
 
     fn send(&mut self, buf: &Self::Buffer) -> ::std::result::Result<Self::Output, Self::Error> {
-        let mut client =
-            self.config
-                .max_idle_connections
-                .map_or_else(|| Client::new(), |max_idle| {
-                    Client::with_pool_config(pool::Config { max_idle: max_idle })
-                });
-
-        client.set_redirect_policy(self.config.redirect_policy);
-        client.set_read_timeout(self.config.read_timeout);
-        client.set_write_timeout(self.config.write_timeout);
-
-        let res = client
-            .post(self.base.clone())
-            .body(buf.as_ref())
-            .headers(self.config.headers())
-            .send()?;
+        self.sender.post(buf.as_ref())
+    }
+}
 
-        if res.status != hyper::Ok {
-            let err: Error = ErrorKind::ResponseError(res.status).into();
+/// A non-blocking HTTP/1.1 client, the pending batches hold no thread
+/// and share at most `max_in_flight` keep-alive connections to the collector.
+///
+/// The sockets are driven by the default tokio reactor.
+/// Only the `http` scheme is supported, and the host is resolved once when created.
+pub struct AsyncHttpTransport<B, E> {
+    /// The request line and the headers, without the content length
+    head: String,
+    pool: Arc<Pool>,
+    phantom: PhantomData<fn(B) -> E>,
+}
 
-            bail!(err)
-        } else {
-            Ok(())
+impl<B, E> AsyncHttpTransport<B, E> {
+    /// Send the spans to the collector at `base`,
+    /// with at most `max_in_flight` batches at the same time.
+    pub fn new(base: &str, config: HttpConfig) -> Result<Self> {
+        let url = spans_url(base, &config)?;
+
+        if url.scheme() != "http" {
+            bail!(ErrorKind::UnsupportedScheme(url.scheme().to_owned()))
         }
+
+        let addr = url.to_socket_addrs()?
+            .next()
+            .ok_or_else(|| Error::from(ErrorKind::UnresolvedHost(url.to_string())))?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_owned(),
+            (None, _) => bail!(ErrorKind::UnresolvedHost(url.to_string())),
+        };
+        let target = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_owned(),
+        };
+
+        Ok(AsyncHttpTransport {
+               head: format!("POST {} HTTP/1.1\r\nHost: {}\r\n{}",
+                             target,
+                             host,
+                             config.headers()),
+               pool: Arc::new(Pool::new(addr, config.max_in_flight)),
+               phantom: PhantomData,
+           })
+    }
+
+    fn request(&self, body: &[u8]) -> Vec<u8> {
+        let mut request = format!("{}Content-Length: {}\r\n\r\n", self.head, body.len())
+            .into_bytes();
+
+        request.extend_from_slice(body);
+
+        request
+    }
+}
+
+impl<B, E> AsyncTransport for AsyncHttpTransport<B, E>
+    where B: 'static + AsRef<[u8]> + Send,
+          E: 'static + From<::hyper::Error> + From<Error> + Send
+{
+    type Buffer = B;
+    type Output = ();
+    type Error = E;
+    type Future = Box<Future<Item = Self::Output, Error = Self::Error> + Send>;
+
+    fn async_send(&self, buf: Self::Buffer) -> Self::Future {
+        let request = self.request(buf.as_ref());
+
+        Box::new(Pool::send(&self.pool, request).then(|res| match res {
+            Ok(status) => {
+                let status = StatusCode::from_u16(status);
+
+                if status.is_success() {
+                    Ok(())
+                } else {
+                    let err: Error = ErrorKind::ResponseError(status).into();
+
+                    Err(err.into())
+                }
+            }
+            Err(err) => Err(Error::from(err).into()),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::prelude::*;
+    use std::thread;
+    use std::net::{SocketAddr, TcpListener};
+    use std::sync::Mutex;
+
+    use hyper::server::{Server, Request, Response};
+
+    use futures::future;
+
+    use super::*;
+
+    struct Codec(&'static str, ApiVersion);
+
+    impl MimeType for Codec {
+        fn mime_type(&self) -> Mime {
+            self.0.parse().unwrap()
+        }
+
+        fn content_encoding(&self) -> Option<ContentEncoding> {
+            Some(ContentEncoding::Gzip)
+        }
+
+        fn api_version(&self) -> ApiVersion {
+            self.1
+        }
+    }
+
+    fn transport(base: &str, codec: Codec) -> Result<HttpTransport<Vec<u8>, Error>> {
+        HttpTransport::new(base, HttpConfig::from_codec(&codec))
+    }
+
+    #[test]
+    fn negotiate() {
+        let config = HttpConfig::from_codec(&Codec("application/json", ApiVersion::V2));
+
+        assert_eq!(config.content_type, "application/json".parse::<Mime>().unwrap());
+        assert_eq!(config.content_encoding, Some(ContentEncoding::Gzip));
+        assert_eq!(config.api_version, ApiVersion::V2);
+        assert_eq!(config.headers().get_raw("Content-Encoding").unwrap(),
+                   &[b"gzip".to_vec()][..]);
+
+        for &(base, mime, version, url) in
+            &[("http://localhost:9411", "application/json", ApiVersion::V1,
+               "http://localhost:9411/api/v1/spans"),
+              ("http://localhost:9411/", "application/json", ApiVersion::V2,
+               "http://localhost:9411/api/v2/spans"),
+              ("http://localhost:9411/api/v1/spans", "application/x-thrift", ApiVersion::V1,
+               "http://localhost:9411/api/v1/spans"),
+              ("http://proxy/zipkin", "application/json", ApiVersion::V2, "http://proxy/zipkin"),
+              ("http://proxy/ingest", "application/x-ndjson", ApiVersion::V2,
+               "http://proxy/ingest")] {
+            assert_eq!(transport(base, Codec(mime, version)).unwrap().sender.url.as_str(), url);
+        }
+
+        for &(mime, version) in &[("application/x-thrift", ApiVersion::V2),
+                                  ("application/vnd.apache.thrift.compact", ApiVersion::V1),
+                                  ("application/x-ndjson", ApiVersion::V1)] {
+            match transport("http://localhost:9411", Codec(mime, version)) {
+                Err(Error(ErrorKind::UnsupportedContentType(..), _)) => {}
+                Err(err) => panic!("unexpected error: {}", err),
+                Ok(_) => panic!("accepted `{}` for the {:?} API", mime, version),
+            }
+        }
+
+        match transport("http://localhost:9411/api/v1/spans",
+                        Codec("application/json", ApiVersion::V2)) {
+            Err(Error(ErrorKind::MismatchedPath(ref path, ApiVersion::V2), _)) => {
+                assert_eq!(path, "/api/v1/spans")
+            }
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("accepted the mismatched path"),
+        }
+    }
+
+    /// The remote address, path and body of the requests received by the collector
+    type Received = Arc<Mutex<Vec<(SocketAddr, String, Vec<u8>)>>>;
+
+    fn collector() -> (String, Received) {
+        let received = Received::default();
+        let requests = received.clone();
+        let handler = move |mut req: Request, mut res: Response| {
+            let mut body = vec![];
+
+            req.read_to_end(&mut body).unwrap();
+            requests
+                .lock()
+                .unwrap()
+                .push((req.remote_addr, req.uri.to_string(), body));
+
+            *res.status_mut() = hyper::status::StatusCode::Accepted;
+        };
+        // a keep-alive connection holds a thread of the server
+        let mut listening = Server::http("127.0.0.1:0")
+            .unwrap()
+            .handle_threads(handler, 4)
+            .unwrap();
+        let base = format!("http://{}", listening.socket);
+
+        // the server thread can't be stopped, leave it to the end of the tests
+        listening.close().unwrap();
+
+        (base, received)
+    }
+
+    #[test]
+    fn keep_alive() {
+        let (base, received) = collector();
+        let config = HttpConfig::new("application/json".parse().unwrap());
+        let mut transport = HttpTransport::<_, Error>::new(&base, config).unwrap();
+
+        for batch in &["[1]", "[2]", "[3]"] {
+            transport.send(batch).unwrap();
+        }
+
+        let received = received.lock().unwrap();
+
+        assert_eq!(received.len(), 3);
+        assert!(received.iter().all(|&(addr, ref path, _)| {
+                                        addr == received[0].0 && path == "/api/v1/spans"
+                                    }),
+                "the batches should reuse the connection, {:?}",
+                *received);
+        assert_eq!(received.iter().map(|r| &r.2[..]).collect::<Vec<_>>(),
+                   vec![b"[1]", b"[2]", b"[3]"]);
+    }
+
+    #[test]
+    fn async_send() {
+        let (base, received) = collector();
+        let config = HttpConfig {
+            api_version: ApiVersion::V2,
+            max_in_flight: 2,
+            ..HttpConfig::new("application/json".parse().unwrap())
+        };
+        let transport = AsyncHttpTransport::<_, Error>::new(&base, config).unwrap();
+
+        let pending = (0..8)
+            .map(|i| transport.async_send(format!("[{}]", i)))
+            .collect::<Vec<_>>();
+
+        future::join_all(pending).wait().unwrap();
+
+        let received = received.lock().unwrap();
+        let mut connections = received.iter().map(|r| r.0).collect::<Vec<_>>();
+        let mut bodies = received
+            .iter()
+            .map(|r| String::from_utf8(r.2.clone()).unwrap())
+            .collect::<Vec<_>>();
+
+        connections.sort();
+        connections.dedup();
+        bodies.sort();
+
+        assert_eq!(received.len(), 8);
+        assert!(received.iter().all(|r| r.1 == "/api/v2/spans"));
+        assert!(connections.len() <= 2, "too many connections, {:?}", connections);
+        assert_eq!(bodies, (0..8).map(|i| format!("[{}]", i)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn async_reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+
+        // close every connection after the response, without telling the client
+        let server = thread::spawn(move || for (body, status) in vec![("[1]", "202 Accepted"),
+                                                                     ("[2]", "500 Oops")] {
+            let (mut conn, _) = listener.accept().unwrap();
+            let mut buf = vec![];
+            let mut chunk = [0; 1024];
+
+            while !buf.ends_with(body.as_bytes()) {
+                let read = conn.read(&mut chunk).unwrap();
+
+                assert!(read > 0, "unexpected request, {:?}", String::from_utf8_lossy(&buf));
+                buf.extend_from_slice(&chunk[..read]);
+            }
+
+            write!(conn, "HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status).unwrap();
+        });
+
+        let config = HttpConfig::new("application/json".parse().unwrap());
+        let transport = AsyncHttpTransport::<_, Error>::new(&base, config).unwrap();
+
+        transport.async_send("[1]").wait().unwrap();
+
+        match transport.async_send("[2]").wait() {
+            Err(Error(ErrorKind::ResponseError(hyper::status::StatusCode::InternalServerError),
+                      _)) => {}
+            res => panic!("unexpected result: {:?}", res),
+        }
+
+        server.join().unwrap();
     }
 }
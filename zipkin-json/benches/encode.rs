@@ -0,0 +1,79 @@
+//! Compare the `to_writer` path, which builds a tree of JSON values,
+//! with the `write_json` path streaming the spans to the buffer.
+//!
+//! Run with `cargo bench --features nightly` on the nightly toolchain.
+
+#![feature(test)]
+
+extern crate test;
+extern crate bytes;
+
+extern crate zipkin_core;
+extern crate zipkin_json;
+
+use std::sync::Arc;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use bytes::{BytesMut, BufMut};
+
+use test::Bencher;
+
+use zipkin_core::*;
+use zipkin_json::{to_writer, write_json};
+
+fn spans() -> Vec<Span<'static>> {
+    let endpoint = Some(Arc::new(Endpoint {
+                                     name: Some("bench".into()),
+                                     addr: Some(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127,
+                                                                                         0,
+                                                                                         0,
+                                                                                         1)),
+                                                                8080)),
+                                 }));
+
+    (0..100)
+        .map(|_| {
+            let mut span = Span::new("get").with_kind(Kind::Client);
+
+            span.annotate(CLIENT_SEND, endpoint.clone());
+            span.annotate(CLIENT_RECV, endpoint.clone());
+            span.binary_annotate(HTTP_METHOD, "GET", endpoint.clone());
+            span.binary_annotate(HTTP_URL, "http://localhost/users".to_owned(), None);
+            span.binary_annotate(HTTP_STATUS_CODE, 200i16, None);
+            span.duration = Some(Duration::milliseconds(5));
+            span
+        })
+        .collect()
+}
+
+#[bench]
+fn bench_to_writer(b: &mut Bencher) {
+    let spans = spans();
+    let mut buf = BytesMut::with_capacity(128 * 1024);
+
+    b.iter(|| {
+               buf.clear();
+
+               to_writer(&mut (&mut buf).writer(), &spans).unwrap();
+           });
+    b.bytes = buf.len() as u64;
+}
+
+#[bench]
+fn bench_write_json(b: &mut Bencher) {
+    let spans = spans();
+    let mut buf = BytesMut::with_capacity(128 * 1024);
+
+    b.iter(|| {
+               buf.clear();
+
+               write_json(&spans, &mut buf).unwrap();
+           });
+    b.bytes = buf.len() as u64;
+
+    let mut expected = vec![];
+
+    to_writer(&mut expected, &spans).unwrap();
+
+    assert_eq!(&buf[..], &expected[..]);
+}
@@ -1,15 +1,23 @@
 use std::marker::PhantomData;
 
-use bytes::{BytesMut, BufMut};
+use bytes::BytesMut;
 
 use mime::Mime;
 
-use encode::{ToJson, to_writer, to_writer_pretty};
+use serde::Serialize;
+use serde_json;
 
-use zipkin_core::{Encoder, MimeType};
+use encode::ToJson;
+use writer::{WriteJson, Writer};
+
+use zipkin_core::{Encoder, MimeType, ApiVersion, Limits, Truncate};
 
 pub struct JsonCodec<T, E> {
     pub pretty_print: bool,
+    /// The data model of the encoded spans
+    pub version: ApiVersion,
+    /// The size limits enforced before encoding
+    pub limits: Limits,
     phantom: PhantomData<(T, E)>,
 }
 
@@ -19,6 +27,8 @@ impl<T, E> JsonCodec<T, E> {
     pub fn new() -> Self {
         JsonCodec {
             pretty_print: false,
+            version: ApiVersion::V1,
+            limits: Limits::default(),
             phantom: PhantomData,
         }
     }
@@ -26,25 +36,46 @@ impl<T, E> JsonCodec<T, E> {
     pub fn pretty() -> Self {
         JsonCodec {
             pretty_print: true,
+            version: ApiVersion::V1,
+            limits: Limits::default(),
             phantom: PhantomData,
         }
     }
+
+    pub fn with_version(self, version: ApiVersion) -> Self {
+        JsonCodec { version: version, ..self }
+    }
+
+    pub fn with_limits(self, limits: Limits) -> Self {
+        JsonCodec { limits: limits, ..self }
+    }
 }
 
 impl<T, E> Encoder for JsonCodec<T, E>
-    where T: ToJson,
+    where T: ToJson + WriteJson + Serialize + Truncate,
           E: From<::std::io::Error> + From<::serde_json::Error>
 {
     type Item = T;
     type Error = E;
 
-    fn encode(&mut self, item: Self::Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        let mut buf = dst.writer();
+    fn encode(&mut self, mut item: Self::Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        item.enforce_limits(&self.limits);
+
+        if self.version == ApiVersion::V1 && !self.pretty_print {
+            item.write_json(dst)?;
+
+            return Ok(());
+        }
+
+        let mut buf = Writer(dst);
 
-        if self.pretty_print {
-            to_writer_pretty(&mut buf, &item)?;
-        } else {
-            to_writer(&mut buf, &item)?;
+        match self.version {
+            // the v1 model is serialized to the buffer without the intermediate values
+            ApiVersion::V1 => serde_json::ser::to_writer_pretty(&mut buf, &item)?,
+            ApiVersion::V2 if self.pretty_print => {
+                serde_json::ser::to_writer_pretty(&mut buf, &item.to_json_v2())?
+            }
+            ApiVersion::V2 => serde_json::ser::to_writer(&mut buf, &item.to_json_v2())?,
         }
 
         Ok(())
@@ -55,6 +86,71 @@ impl<T, E> MimeType for JsonCodec<T, E> {
     fn mime_type(&self) -> Mime {
         mime!(Application / Json)
     }
+
+    fn api_version(&self) -> ApiVersion {
+        self.version
+    }
+}
+
+/// Encode the spans as newline delimited JSON, one compact object per line,
+/// for the log shipping pipelines instead of the Zipkin collectors.
+pub struct NdJsonCodec<T, E> {
+    /// The data model of the encoded spans
+    pub version: ApiVersion,
+    /// The size limits enforced before encoding
+    pub limits: Limits,
+    phantom: PhantomData<fn(T) -> E>,
+}
+
+impl<T, E> NdJsonCodec<T, E> {
+    pub fn new() -> Self {
+        NdJsonCodec {
+            version: ApiVersion::V1,
+            limits: Limits::default(),
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn with_version(self, version: ApiVersion) -> Self {
+        NdJsonCodec { version: version, ..self }
+    }
+
+    pub fn with_limits(self, limits: Limits) -> Self {
+        NdJsonCodec { limits: limits, ..self }
+    }
+}
+
+impl<T, E> Encoder for NdJsonCodec<T, E>
+    where T: ToJson + WriteJson + Truncate,
+          E: From<::std::io::Error> + From<::serde_json::Error>
+{
+    type Item = Vec<T>;
+    type Error = E;
+
+    fn encode(&mut self, items: Self::Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        for mut item in items {
+            item.enforce_limits(&self.limits);
+
+            match self.version {
+                ApiVersion::V1 => item.write_json(dst)?,
+                ApiVersion::V2 => dst.extend_from_slice(&serde_json::to_vec(&item.to_json_v2())?),
+            }
+
+            dst.extend_from_slice(b"\n");
+        }
+
+        Ok(())
+    }
+}
+
+impl<T, E> MimeType for NdJsonCodec<T, E> {
+    fn mime_type(&self) -> Mime {
+        mime!(Application / ("x-ndjson"))
+    }
+
+    fn api_version(&self) -> ApiVersion {
+        self.version
+    }
 }
 
 #[cfg(test)]
@@ -75,12 +171,12 @@ mod tests {
                                lo: 123,
                                hi: Some(456),
                            })
-            .with_id(123)
-            .with_parent_id(456)
+            .with_id(SpanId(123))
+            .with_parent_id(SpanId(456))
             .with_debug(true);
         let endpoint =
             Some(Arc::new(Endpoint {
-                              name: Some("test"),
+                              name: Some("test".into()),
                               addr: Some(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
                                                          8080)),
                           }));
@@ -93,4 +189,74 @@ mod tests {
 
         codec.encode(span, &mut buf).unwrap();
     }
+
+    #[test]
+    fn limits() {
+        let mut span = Span::new("test");
+
+        span.binary_annotate(HTTP_URL, "/users/123456", None);
+
+        let limits = Limits::new().with_max_value_length(10);
+        let mut codec = JsonCodec::<_, Error>::new().with_limits(limits.clone());
+        let mut buf = BytesMut::with_capacity(1024);
+
+        codec.encode(vec![span], &mut buf).unwrap();
+
+        assert!(String::from_utf8(buf.to_vec())
+                    .unwrap()
+                    .contains(r#""value":"/users/...""#));
+        assert_eq!(limits.truncations.total(), 1);
+    }
+
+    #[test]
+    fn version() {
+        let mut span = Span::new("test").with_kind(Kind::Client);
+
+        span.binary_annotate(HTTP_METHOD, "GET", None);
+
+        let mut codec = JsonCodec::<_, Error>::new().with_version(ApiVersion::V2);
+        let mut buf = BytesMut::with_capacity(1024);
+
+        codec.encode(vec![span], &mut buf).unwrap();
+
+        let json = String::from_utf8(buf.to_vec()).unwrap();
+
+        assert!(json.contains(r#""kind":"CLIENT""#));
+        assert!(json.contains(r#""tags":{"http.method":"GET"}"#));
+        assert_eq!(codec.api_version(), ApiVersion::V2);
+    }
+
+    #[test]
+    fn ndjson() {
+        let mut span = Span::new("test").with_kind(Kind::Server);
+
+        span.binary_annotate(HTTP_URL, "/users/123456", None);
+
+        let spans = vec![span.clone(), span.child("child")];
+
+        for &version in &[ApiVersion::V1, ApiVersion::V2] {
+            let mut codec = NdJsonCodec::<_, Error>::new().with_version(version);
+            let mut buf = BytesMut::with_capacity(1024);
+
+            codec.encode(spans.clone(), &mut buf).unwrap();
+
+            let ndjson = String::from_utf8(buf.to_vec()).unwrap();
+
+            assert!(ndjson.ends_with("\n"));
+            assert_eq!(ndjson.lines().count(), 2);
+
+            for (line, span) in ndjson.lines().zip(&spans) {
+                let expected = match version {
+                    ApiVersion::V1 => span.to_json(),
+                    ApiVersion::V2 => span.to_json_v2(),
+                };
+
+                assert_eq!(serde_json::from_str::<serde_json::Value>(line).unwrap(),
+                           expected);
+            }
+
+            assert_eq!(codec.mime_type().to_string(), "application/x-ndjson");
+            assert_eq!(codec.api_version(), version);
+        }
+    }
 }
\ No newline at end of file
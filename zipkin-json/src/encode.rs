@@ -1,4 +1,3 @@
-use std::str;
 use std::io::prelude::*;
 use std::string::String;
 use std::net::SocketAddr;
@@ -9,26 +8,26 @@ use serde_json::{Map, Value, Result};
 use base64;
 
 use zipkin_core::{self as zipkin, TraceId, SpanId, Timestamp, ToMicrosecond, Duration, Endpoint,
-                  Annotation, BinaryAnnotation, Span};
+                  Annotation, BinaryAnnotation, Span, CLIENT_ADDR, SERVER_ADDR, MESSAGE_ADDR};
 
 pub trait ToJson {
     fn to_json(&self) -> Value;
+
+    /// Encode with the v2 model, the same as the v1 model by default.
+    fn to_json_v2(&self) -> Value {
+        self.to_json()
+    }
 }
 
 impl ToJson for TraceId {
     fn to_json(&self) -> Value {
-        let id = match self.hi {
-            Some(hi) => format!("{:016x}{:016x}", hi, self.lo),
-            None => format!("{:016x}", self.lo),
-        };
-
-        id.into()
+        self.to_string().into()
     }
 }
 
 impl ToJson for SpanId {
     fn to_json(&self) -> Value {
-        format!("{:016x}", self).into()
+        self.to_string().into()
     }
 }
 
@@ -40,16 +39,18 @@ impl ToJson for Timestamp {
 
 impl ToJson for Duration {
     fn to_json(&self) -> Value {
-        self.num_milliseconds().into()
+        self.to_microseconds().into()
     }
 }
 
+/// The v1 model is built as a tree of values, independent of the `Serialize` implementation
+/// streamed by `WriteJson`, so each of them could be checked against the other.
 impl<'a> ToJson for Endpoint<'a> {
     fn to_json(&self) -> Value {
         let mut attrs = Map::new();
 
-        if let Some(name) = self.name {
-            attrs.insert("serviceName".into(), name.into());
+        if let Some(ref name) = self.name {
+            attrs.insert("serviceName".into(), name.as_ref().into());
         }
 
         match self.addr {
@@ -79,7 +80,7 @@ impl<'a> ToJson for Annotation<'a> {
         let mut attrs = Map::new();
 
         attrs.insert("timestamp".into(), self.timestamp.to_json());
-        attrs.insert("value".into(), self.value.into());
+        attrs.insert("value".into(), self.value.as_ref().into());
         if let Some(ref endpoint) = self.endpoint {
             attrs.insert("endpoint".into(), endpoint.to_json());
         }
@@ -92,11 +93,12 @@ impl<'a> ToJson for BinaryAnnotation<'a> {
     fn to_json(&self) -> Value {
         let mut attrs = Map::new();
 
-        attrs.insert("key".into(), self.key.into());
+        attrs.insert("key".into(), self.key.as_ref().into());
 
         let (value, ty) = match self.value {
             zipkin::Value::Bool(v) => (v.into(), None),
             zipkin::Value::Bytes(v) => (base64::encode(v).into(), Some("BYTES")),
+            zipkin::Value::Blob(ref v) => (base64::encode(v).into(), Some("BYTES")),
             zipkin::Value::I16(v) => (v.into(), Some("I16")),
             zipkin::Value::I32(v) => (v.into(), Some("I32")),
             zipkin::Value::I64(v) => (v.into(), Some("I64")),
@@ -124,7 +126,7 @@ impl<'a> ToJson for Span<'a> {
 
         attrs.insert("traceId".into(), self.trace_id.to_json());
         attrs.insert("id".into(), self.id.to_json());
-        attrs.insert("name".into(), self.name.into());
+        attrs.insert("name".into(), self.name.as_ref().into());
         if let Some(id) = self.parent_id {
             attrs.insert("parentId".into(), id.to_json());
         }
@@ -140,10 +142,12 @@ impl<'a> ToJson for Span<'a> {
                              .collect::<Vec<Value>>()
                              .into());
         }
-        if !self.binary_annotations.is_empty() {
+        let remote_address = self.remote_address();
+        if !self.binary_annotations.is_empty() || remote_address.is_some() {
             attrs.insert("binaryAnnotations".into(),
                          self.binary_annotations
                              .iter()
+                             .chain(remote_address.as_ref())
                              .map(|annotation| annotation.to_json())
                              .collect::<Vec<Value>>()
                              .into());
@@ -154,6 +158,115 @@ impl<'a> ToJson for Span<'a> {
 
         attrs.into()
     }
+
+    fn to_json_v2(&self) -> Value {
+        let mut attrs = Map::new();
+
+        attrs.insert("traceId".into(), self.trace_id.to_json());
+        attrs.insert("id".into(), self.id.to_json());
+        attrs.insert("name".into(), self.name.as_ref().into());
+        if let Some(id) = self.parent_id {
+            attrs.insert("parentId".into(), id.to_json());
+        }
+        if let Some(kind) = self.kind {
+            attrs.insert("kind".into(), kind.as_str().into());
+        }
+        attrs.insert("timestamp".into(), self.timestamp.to_json());
+        if let Some(d) = self.duration {
+            attrs.insert("duration".into(), d.to_json());
+        }
+
+        let local_endpoint = self.local_endpoint
+            .as_ref()
+            .or_else(|| {
+                         self.annotations
+                             .iter()
+                             .flat_map(|annotation| annotation.endpoint.as_ref())
+                             .next()
+                     });
+        if let Some(endpoint) = local_endpoint {
+            attrs.insert("localEndpoint".into(), endpoint.to_json());
+        }
+
+        // the v1 address annotations are converted to the remote endpoint
+        let (addresses, tags): (Vec<&BinaryAnnotation>, Vec<&BinaryAnnotation>) =
+            self.binary_annotations
+                .iter()
+                .partition(|annotation| is_address(annotation));
+        let remote_endpoint = self.remote_endpoint
+            .as_ref()
+            .or_else(|| {
+                         addresses
+                             .iter()
+                             .flat_map(|annotation| annotation.endpoint.as_ref())
+                             .next()
+                     });
+        if let Some(endpoint) = remote_endpoint {
+            attrs.insert("remoteEndpoint".into(), endpoint.to_json());
+        }
+
+        // the core annotations are implied by the span kind
+        let core_annotations = self.kind.map(|kind| kind.annotations());
+        let annotations = self.annotations
+            .iter()
+            .filter(|annotation| {
+                        core_annotations.map_or(true, |(start, finish)| {
+                            annotation.value != start && annotation.value != finish
+                        })
+                    })
+            .collect::<Vec<&Annotation>>();
+        if !annotations.is_empty() {
+            attrs.insert("annotations".into(),
+                         annotations
+                             .iter()
+                             .map(|annotation| {
+                                      let mut attrs = Map::new();
+
+                                      attrs.insert("timestamp".into(),
+                                                   annotation.timestamp.to_json());
+                                      attrs.insert("value".into(), annotation.value.as_ref().into());
+
+                                      Value::from(attrs)
+                                  })
+                             .collect::<Vec<Value>>()
+                             .into());
+        }
+        if !tags.is_empty() {
+            attrs.insert("tags".into(),
+                         tags.iter()
+                             .map(|annotation| {
+                                      (annotation.key.to_string(),
+                                       tag_value(&annotation.value).into())
+                                  })
+                             .collect::<Map<String, Value>>()
+                             .into());
+        }
+        if let Some(debug) = self.debug {
+            attrs.insert("debug".into(), debug.into());
+        }
+
+        attrs.into()
+    }
+}
+
+fn is_address(annotation: &BinaryAnnotation) -> bool {
+    annotation.value == zipkin::Value::Bool(true) &&
+    [CLIENT_ADDR, SERVER_ADDR, MESSAGE_ADDR].contains(&&*annotation.key)
+}
+
+/// The tags of the v2 model are always strings
+fn tag_value(value: &zipkin::Value) -> String {
+    match *value {
+        zipkin::Value::Bool(v) => v.to_string(),
+        zipkin::Value::Bytes(v) => base64::encode(v),
+        zipkin::Value::Blob(ref v) => base64::encode(v),
+        zipkin::Value::I16(v) => v.to_string(),
+        zipkin::Value::I32(v) => v.to_string(),
+        zipkin::Value::I64(v) => v.to_string(),
+        zipkin::Value::Double(v) => v.to_string(),
+        zipkin::Value::Str(v) => v.to_owned(),
+        zipkin::Value::String(ref v) => v.clone(),
+    }
 }
 
 impl<'a, T: ToJson> ToJson for &'a [T] {
@@ -163,15 +276,22 @@ impl<'a, T: ToJson> ToJson for &'a [T] {
             .collect::<Vec<Value>>()
             .into()
     }
+
+    fn to_json_v2(&self) -> Value {
+        self.iter()
+            .map(|item| item.to_json_v2())
+            .collect::<Vec<Value>>()
+            .into()
+    }
 }
 
 impl<T: ToJson> ToJson for Vec<T> {
     fn to_json(&self) -> Value {
-        self.as_slice()
-            .iter()
-            .map(|item| item.to_json())
-            .collect::<Vec<Value>>()
-            .into()
+        self.as_slice().to_json()
+    }
+
+    fn to_json_v2(&self) -> Value {
+        self.as_slice().to_json_v2()
     }
 }
 
@@ -221,12 +341,12 @@ mod tests {
                                lo: 123,
                                hi: Some(456),
                            })
-            .with_id(123)
-            .with_parent_id(456)
+            .with_id(SpanId(123))
+            .with_parent_id(SpanId(456))
             .with_debug(true);
         let endpoint =
             Some(Arc::new(Endpoint {
-                              name: Some("test"),
+                              name: Some("test".into()),
                               addr: Some(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
                                                          8080)),
                           }));
@@ -258,6 +378,157 @@ mod tests {
                 .collect();
 
         assert_eq!(diffs, Vec::<String>::new());
+        assert_eq!(serde_json::to_value(&span).unwrap(), span.to_json());
+    }
+
+    #[test]
+    fn deserialize() {
+        let span: Span = serde_json::from_slice(PRETTY_JSON).unwrap();
+
+        assert_eq!(span.trace_id,
+                   TraceId {
+                       lo: 123,
+                       hi: Some(456),
+                   });
+        assert_eq!(span.id, SpanId(123));
+        assert_eq!(span.parent_id, Some(SpanId(456)));
+        assert_eq!(span.name, "test");
+        assert_eq!(span.timestamp, timestamp(0, 0));
+        assert_eq!(span.debug, Some(true));
+        assert_eq!(span.annotations.len(), 2);
+        assert_eq!(span.annotations[0].value, CLIENT_SEND);
+        assert_eq!(span.annotations[0].endpoint.as_ref().unwrap().addr,
+                   Some(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080)));
+        assert!(span.annotations[1].endpoint.is_none());
+        assert_eq!(span.binary_annotations
+                       .iter()
+                       .map(|annotation| (&*annotation.key, annotation.value.clone()))
+                       .collect::<Vec<_>>(),
+                   vec![(HTTP_METHOD, zipkin_core::Value::Str("GET")),
+                        ("debug", zipkin_core::Value::Bool(true)),
+                        (HTTP_STATUS_CODE, zipkin_core::Value::I16(123)),
+                        (HTTP_REQUEST_SIZE, zipkin_core::Value::I32(-456)),
+                        (HTTP_RESPONSE_SIZE, zipkin_core::Value::I64(-789)),
+                        ("time", zipkin_core::Value::Double(123.456)),
+                        ("raw", zipkin_core::Value::Blob(b"some\0raw\0data".to_vec()))]);
+
+        assert_eq!(serde_json::to_value(&span).unwrap(),
+                   serde_json::from_slice::<serde_json::Value>(PRETTY_JSON).unwrap());
+
+        assert!(serde_json::from_str::<Span>(r#"{"id": "00000000000001c8", "name": "test"}"#)
+                    .is_err());
+        assert!(serde_json::from_str::<Span>(r#"{"traceId": "0", "id": "1", "name": "test"}"#)
+                    .is_err());
+
+        let json = r#"{"traceId": "00000000000001c8", "id": "00000000000001c8",
+                       "name": "get \"users\"", "timestamp": 1,
+                       "annotations": [{"timestamp": 1, "value": "cs\u0021",
+                                        "endpoint": {"serviceName": "w\u00f6rker"}}],
+                       "binaryAnnotations": [{"key": "http\/path", "value": "\/users"}]}"#;
+        let span = serde_json::from_str::<Span>(json).unwrap();
+
+        assert_eq!(span.name, "get \"users\"");
+        assert_eq!(span.annotations[0].value, "cs!");
+        assert_eq!(span.annotations[0].endpoint.as_ref().unwrap().name,
+                   Some("wörker".into()));
+        assert_eq!(span.binary_annotations[0].key, "http/path");
+        assert_eq!(span.binary_annotations[0].value,
+                   zipkin_core::Value::String("/users".to_owned()));
+
+        let json = r#"{"traceId": "00000000000001c8", "id": "00000000000001c8", "name": "test",
+                       "timestamp": 1,
+                       "binaryAnnotations": [{"key": "k", "value": 65536, "type": "I16"}]}"#;
+
+        assert!(serde_json::from_str::<Span>(json).is_err());
+
+        let json = r#"{"traceId": "00000000000001c8", "id": "00000000000001c8", "name": "test",
+                       "timestamp": 1,
+                       "binaryAnnotations": [{"key": "k", "value": 9223372036854775808}]}"#;
+
+        assert!(serde_json::from_str::<Span>(json).is_err());
+
+        // the missing timestamp is rejected instead of being read from the clock
+        let json = r#"{"traceId": "00000000000001c8", "id": "00000000000001c8", "name": "test"}"#;
+
+        assert!(serde_json::from_str::<Span>(json).is_err());
+    }
+
+    #[test]
+    fn remote_endpoint() {
+        let local = Arc::new(Endpoint {
+                                 name: Some("proxy".into()),
+                                 addr: Some(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+                                                            8080)),
+                             });
+        let mut span = Span::new("get")
+            .with_trace_id(TraceId { lo: 123, hi: None })
+            .with_id(SpanId(456))
+            .with_local_endpoint(local);
+
+        span.set_remote_endpoint(Kind::Server,
+                                 Endpoint {
+                                     name: None,
+                                     addr: Some(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10,
+                                                                                         0,
+                                                                                         0,
+                                                                                         1)),
+                                                                54321)),
+                                 });
+        span.annotate(SERVER_RECV, None);
+        span.annotate(WIRE_RECV, None);
+        span.binary_annotate(HTTP_STATUS_CODE, 200i16, None);
+        span.annotations[0].timestamp = timestamp(0, 0);
+        span.annotations[1].timestamp = timestamp(0, 0);
+        span.timestamp = timestamp(0, 0);
+        span.duration = Some(Duration::milliseconds(5));
+
+        let v1: serde_json::Value = serde_json::from_str(r#"{
+            "traceId": "000000000000007b",
+            "id": "00000000000001c8",
+            "name": "get",
+            "timestamp": 0,
+            "duration": 5000,
+            "annotations": [{
+                "timestamp": 0,
+                "value": "sr",
+                "endpoint": { "serviceName": "proxy", "ipv4": "127.0.0.1", "port": 8080 }
+            }, {
+                "timestamp": 0,
+                "value": "wr",
+                "endpoint": { "serviceName": "proxy", "ipv4": "127.0.0.1", "port": 8080 }
+            }],
+            "binaryAnnotations": [{
+                "key": "http.status_code",
+                "type": "I16",
+                "value": 200,
+                "endpoint": { "serviceName": "proxy", "ipv4": "127.0.0.1", "port": 8080 }
+            }, {
+                "key": "ca",
+                "value": true,
+                "endpoint": { "ipv4": "10.0.0.1", "port": 54321 }
+            }]
+        }"#)
+                .unwrap();
+
+        assert_eq!(span.to_json(), v1);
+        assert_eq!(serde_json::to_value(&span).unwrap(), v1);
+
+        let v2: serde_json::Value = serde_json::from_str(r#"{
+            "traceId": "000000000000007b",
+            "id": "00000000000001c8",
+            "name": "get",
+            "kind": "SERVER",
+            "timestamp": 0,
+            "duration": 5000,
+            "localEndpoint": { "serviceName": "proxy", "ipv4": "127.0.0.1", "port": 8080 },
+            "remoteEndpoint": { "ipv4": "10.0.0.1", "port": 54321 },
+            "annotations": [{ "timestamp": 0, "value": "wr" }],
+            "tags": { "http.status_code": "200" }
+        }"#)
+                .unwrap();
+
+        assert_eq!(span.to_json_v2(), v2);
+        assert_eq!(vec![span].to_json_v2(), serde_json::Value::Array(vec![v2]));
     }
 
     const PRETTY_JSON: &'static [u8] = br#"{
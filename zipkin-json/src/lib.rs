@@ -1,5 +1,6 @@
 #[macro_use]
 extern crate error_chain;
+extern crate serde;
 extern crate serde_json;
 extern crate base64;
 extern crate bytes;
@@ -15,8 +16,12 @@ pub use serde_json::Error as JsonError;
 
 pub mod errors;
 mod encode;
+mod writer;
 mod codec;
+mod sampler;
 
 pub use encode::{ToJson, to_json, to_string, to_string_pretty, to_vec, to_vec_pretty, to_writer,
                  to_writer_pretty};
-pub use codec::JsonCodec;
\ No newline at end of file
+pub use writer::{WriteJson, write_json};
+pub use codec::{JsonCodec, NdJsonCodec};
+pub use sampler::sampler_from_json;
\ No newline at end of file
@@ -0,0 +1,191 @@
+use std::time::Duration;
+
+use serde_json::{self, Value};
+
+use zipkin_core::{Span, BoxSampler, FixedRate, Probability, RateLimit, Adaptive, Pattern, Rule,
+                  RuleSampler};
+use zipkin_core::errors::{ErrorKind, Result};
+
+/// Load the sampler from the JSON configuration, it could be used with `Reloadable`.
+///
+/// ```json
+/// {
+///   "type": "rules",
+///   "rules": [
+///     { "tags": { "http.path": "/checkout" }, "sampler": { "type": "probability", "probability": 1.0 } },
+///     { "tags": { "http.path": "/health" }, "sampler": { "type": "probability", "probability": 0.01 } },
+///     { "name": "ping*", "sampler": { "type": "probability", "probability": 0.0 } }
+///   ],
+///   "default": { "type": "rate_limit", "quantum": 100, "capacity": 200, "interval": 1000 }
+/// }
+/// ```
+///
+/// The supported sampler types are `fixed_rate`, `probability`, `rate_limit`, `adaptive` and `rules`,
+/// and the `interval` is in milliseconds.
+pub fn sampler_from_json<'a>(buf: &[u8]) -> Result<BoxSampler<'a, Span<'a>>> {
+    let config: Value = serde_json::from_slice(buf)
+        .map_err(|err| ErrorKind::InvalidConfig(err.to_string()))?;
+
+    parse_sampler(&config)
+}
+
+fn invalid<T>(reason: &str) -> Result<T> {
+    bail!(ErrorKind::InvalidConfig(reason.to_owned()))
+}
+
+fn parse_sampler<'a>(config: &Value) -> Result<BoxSampler<'a, Span<'a>>> {
+    match config.get("type").and_then(|v| v.as_str()) {
+        Some("fixed_rate") => {
+            match config.get("sample_rate").and_then(|v| v.as_u64()) {
+                Some(sample_rate) if sample_rate > 0 => {
+                    Ok(Box::new(FixedRate::new(sample_rate as usize)))
+                }
+                _ => invalid("`sample_rate` should be a positive integer"),
+            }
+        }
+        Some("probability") => {
+            match config.get("probability").and_then(|v| v.as_f64()) {
+                Some(probability) if 0.0 <= probability && probability <= 1.0 => {
+                    Ok(Box::new(Probability::new(probability)))
+                }
+                _ => invalid("`probability` should be a number between 0.0 and 1.0"),
+            }
+        }
+        Some("rate_limit") => {
+            let quantum = config.get("quantum").and_then(|v| v.as_u64());
+            let capacity = config.get("capacity").and_then(|v| v.as_u64());
+            let interval = config.get("interval").and_then(|v| v.as_u64()).unwrap_or(1000);
+
+            match (quantum, capacity) {
+                (Some(quantum), capacity) if quantum > 0 && capacity != Some(0) &&
+                                             interval > 0 => {
+                    Ok(Box::new(RateLimit::new(quantum as usize,
+                                               capacity.unwrap_or(quantum) as usize,
+                                               Duration::from_millis(interval))))
+                }
+                _ => invalid("`quantum`, `capacity` and `interval` should be positive integers"),
+            }
+        }
+        Some("adaptive") => {
+            let target = config.get("target").and_then(|v| v.as_f64());
+            let interval = config.get("interval").and_then(|v| v.as_u64()).unwrap_or(1000);
+
+            match target {
+                Some(target) if target > 0.0 && interval > 0 => {
+                    Ok(Box::new(Adaptive::new(target, Duration::from_millis(interval))))
+                }
+                _ => invalid("`target` and `interval` should be positive numbers"),
+            }
+        }
+        Some("rules") => {
+            let mut sampler = match config.get("default") {
+                Some(default) => RuleSampler::with_default(parse_sampler(default)?),
+                None => RuleSampler::new(),
+            };
+
+            for rule in config
+                    .get("rules")
+                    .and_then(|v| v.as_array())
+                    .map_or(&[][..], |rules| rules.as_slice()) {
+                sampler = sampler.rule(parse_rule(rule)?);
+            }
+
+            Ok(Box::new(sampler))
+        }
+        Some(ty) => invalid(&format!("unknown sampler type `{}`", ty)),
+        None => invalid("missing sampler type"),
+    }
+}
+
+fn parse_rule<'a>(config: &Value) -> Result<Rule<'a>> {
+    let mut rule = match config.get("sampler") {
+        Some(sampler) => Rule::new(parse_sampler(sampler)?),
+        None => return invalid("missing sampler of rule"),
+    };
+
+    if let Some(name) = config.get("name") {
+        rule = rule.with_name(parse_pattern(name)?);
+    }
+
+    if let Some(service) = config.get("service") {
+        rule = rule.with_service(parse_pattern(service)?);
+    }
+
+    if let Some(tags) = config.get("tags") {
+        match tags.as_object() {
+            Some(tags) => {
+                for (key, pattern) in tags {
+                    rule = rule.with_tag(key, parse_pattern(pattern)?);
+                }
+            }
+            None => return invalid("`tags` should be an object"),
+        }
+    }
+
+    Ok(rule)
+}
+
+fn parse_pattern(config: &Value) -> Result<Pattern> {
+    match config.as_str() {
+        Some(pattern) => Ok(Pattern::new(pattern)),
+        None => invalid("pattern should be a string"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zipkin_core::*;
+    use zipkin_core::errors::Error;
+
+    use super::*;
+
+    #[test]
+    fn load_sampler() {
+        let sampler = sampler_from_json(br#"{ "type": "fixed_rate", "sample_rate": 2 }"#).unwrap();
+
+        assert!(sampler.sample(&Span::new("test")));
+        assert!(!sampler.sample(&Span::new("test")));
+
+        let sampler = sampler_from_json(br#"{
+            "type": "rules",
+            "rules": [
+                { "tags": { "http.path": "/checkout" }, "sampler": { "type": "probability", "probability": 1.0 } },
+                { "name": "ping*", "sampler": { "type": "probability", "probability": 0.0 } }
+            ],
+            "default": { "type": "rate_limit", "quantum": 1, "interval": 60000 }
+        }"#)
+                .unwrap();
+
+        let mut span = Span::new("request");
+
+        span.binary_annotate(HTTP_PATH, "/checkout", None);
+
+        assert!(sampler.sample(&span));
+        assert!(sampler.sample(&span));
+        assert!(!sampler.sample(&Span::new("ping")));
+        assert!(sampler.sample(&Span::new("request")));
+        assert!(!sampler.sample(&Span::new("request")));
+
+        assert!(sampler_from_json(br#"{ "type": "adaptive", "target": 100 }"#).is_ok());
+    }
+
+    #[test]
+    fn reject_invalid() {
+        for config in &[&b"not json"[..],
+                        br#"{}"#,
+                        br#"{ "type": "unknown" }"#,
+                        br#"{ "type": "fixed_rate", "sample_rate": 0 }"#,
+                        br#"{ "type": "probability", "probability": 1.5 }"#,
+                        br#"{ "type": "rate_limit" }"#,
+                        br#"{ "type": "rate_limit", "quantum": 0 }"#,
+                        br#"{ "type": "rate_limit", "quantum": 1, "capacity": 0 }"#,
+                        br#"{ "type": "rules", "rules": [{ "name": "test" }] }"#,
+                        br#"{ "type": "rules", "rules": [{ "name": 1, "sampler": { "type": "probability", "probability": 1.0 } }] }"#] {
+            match sampler_from_json(config) {
+                Err(Error(ErrorKind::InvalidConfig(_), _)) => {}
+                _ => panic!("should reject invalid configuration: {}",
+                            String::from_utf8_lossy(config)),
+            }
+        }
+    }
+}
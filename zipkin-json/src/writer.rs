@@ -0,0 +1,121 @@
+use std::io;
+
+use bytes::BytesMut;
+
+use serde::Serialize;
+use serde_json::{self, Result};
+
+/// Write the v1 JSON to the buffer directly, without building the intermediate values.
+///
+/// It streams the `Serialize` implementation of the span model with the object keys sorted,
+/// the output is the same as the value tree built by `to_writer`.
+pub trait WriteJson {
+    fn write_json(&self, dst: &mut BytesMut) -> Result<()>;
+}
+
+impl<T: Serialize + ?Sized> WriteJson for T {
+    fn write_json(&self, dst: &mut BytesMut) -> Result<()> {
+        serde_json::to_writer(Writer(dst), self)
+    }
+}
+
+/// Write to the buffer, which grows on demand instead of failing when it was full.
+pub struct Writer<'a>(pub &'a mut BytesMut);
+
+impl<'a> io::Write for Writer<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.extend_from_slice(buf);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn write_json<T: WriteJson + ?Sized>(value: &T, dst: &mut BytesMut) -> Result<()> {
+    value.write_json(dst)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+    use zipkin_core::*;
+
+    use super::*;
+    use encode::to_vec;
+
+    fn write<T: WriteJson + ?Sized>(value: &T) -> String {
+        let mut buf = BytesMut::with_capacity(16);
+
+        write_json(value, &mut buf).unwrap();
+
+        String::from_utf8(buf.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn write_value() {
+        assert_eq!(write("a\"b\\c\n\u{1}中"), r#""a\"b\\c\n\u0001中""#);
+        assert_eq!(write(&i64::min_value()), i64::min_value().to_string());
+        assert_eq!(write(&0i64), "0");
+        assert_eq!(write(&123.456), "123.456");
+        assert_eq!(write(&TraceId {
+                             lo: 123,
+                             hi: Some(456),
+                         }),
+                   r#""00000000000001c8000000000000007b""#);
+        assert_eq!(write(&SpanId(0xffff)), r#""000000000000ffff""#);
+    }
+
+    #[test]
+    fn same_as_to_writer() {
+        let mut span = Span::new("test\t\"quoted\"")
+            .with_trace_id(TraceId {
+                               lo: 123,
+                               hi: Some(456),
+                           })
+            .with_id(SpanId(123))
+            .with_parent_id(SpanId(456))
+            .with_kind(Kind::Client)
+            .with_debug(true);
+        let endpoint =
+            Some(Arc::new(Endpoint {
+                              name: Some("test".into()),
+                              addr: Some(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+                                                         8080)),
+                          }));
+
+        span.set_remote_endpoint(Kind::Client,
+                                 Endpoint {
+                                     name: Some("backend".into()),
+                                     addr: Some(SocketAddr::new(IpAddr::V6(Ipv6Addr::new(0xfe80,
+                                                                                         0,
+                                                                                         0,
+                                                                                         0,
+                                                                                         0,
+                                                                                         0,
+                                                                                         0,
+                                                                                         1)),
+                                                                0)),
+                                 });
+        span.annotate(CLIENT_SEND, endpoint.clone());
+        span.annotate(CLIENT_RECV, None);
+        span.binary_annotate(HTTP_METHOD, "GET", endpoint.clone());
+        span.binary_annotate(HTTP_URL, "/users?name=\u{1b}".to_owned(), None);
+        span.binary_annotate("debug", true, None);
+        span.binary_annotate(HTTP_STATUS_CODE, 123i16, None);
+        span.binary_annotate(HTTP_REQUEST_SIZE, -456i32, None);
+        span.binary_annotate(HTTP_RESPONSE_SIZE, -789i64, None);
+        span.binary_annotate("time", 1e-7, None);
+        span.binary_annotate("raw", &b"some\0raw\0data"[..], None);
+        span.duration = Some(Duration::milliseconds(5));
+
+        let spans = vec![span.clone(), span.with_debug(false)];
+
+        assert_eq!(write(&spans), String::from_utf8(to_vec(&spans).unwrap()).unwrap());
+        assert_eq!(write(&Vec::<Span>::new()), "[]");
+    }
+}
@@ -0,0 +1,128 @@
+//! Check the JSON codecs with the span corpus and the golden payloads in `testdata`,
+//! which are validated with the JSON schemas of Zipkin's API in `testdata/schema`.
+
+extern crate bytes;
+extern crate serde_json;
+extern crate jsonschema;
+extern crate zipkin_core;
+extern crate zipkin_json;
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::PathBuf;
+
+use bytes::BytesMut;
+
+use serde_json::Value;
+
+use jsonschema::JSONSchema;
+
+use zipkin_core::{ApiVersion, Encoder, Span};
+use zipkin_json::{JsonCodec, ToJson, to_string_pretty, write_json};
+use zipkin_json::errors::Error;
+
+include!("../../testdata/corpus.rs");
+
+fn golden(path: &str) -> Vec<u8> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../testdata").join(path);
+    let mut buf = vec![];
+
+    File::open(&path)
+        .and_then(|mut f| f.read_to_end(&mut buf))
+        .unwrap_or_else(|err| panic!("fail to read golden file {:?}, {}", path, err));
+
+    buf
+}
+
+/// Validate the spans with the JSON schema converted from Zipkin's OpenAPI spec
+fn validate(schema: &str, spans: &Value, name: &str) {
+    let schema: Value = serde_json::from_slice(&golden(schema)).unwrap();
+    let schema = JSONSchema::compile(&schema).unwrap();
+
+    let errors = match schema.validate(spans) {
+        Ok(()) => vec![],
+        Err(errors) => {
+            errors
+                .map(|err| format!("{} at {}", err, err.instance_path))
+                .collect()
+        }
+    };
+
+    assert!(errors.is_empty(),
+            "{} violates the schema: {}",
+            name,
+            errors.join(", "));
+}
+
+fn encode(version: ApiVersion, span: &Span) -> Vec<u8> {
+    let mut codec = JsonCodec::<_, Error>::new().with_version(version);
+    let mut buf = BytesMut::with_capacity(4096);
+
+    codec.encode(vec![span.clone()], &mut buf).unwrap();
+
+    buf.to_vec()
+}
+
+#[test]
+fn v1_golden() {
+    for (name, span) in corpus::spans() {
+        let expected = golden(&format!("v1/{}.json", name));
+        let value: Value = serde_json::from_slice(&expected).unwrap();
+
+        validate("schema/zipkin-api.json", &Value::Array(vec![value.clone()]), name);
+
+        assert_eq!(to_string_pretty(&span).unwrap(),
+                   String::from_utf8_lossy(&expected).trim_right(),
+                   "{}",
+                   name);
+        assert_eq!(serde_json::to_value(&span).unwrap(), value, "{}", name);
+
+        // the streaming writer must produce the compact golden byte for byte
+        let mut buf = BytesMut::with_capacity(64);
+
+        write_json(&span, &mut buf).unwrap();
+
+        assert_eq!(String::from_utf8_lossy(&buf),
+                   serde_json::to_string(&value).unwrap(),
+                   "{}",
+                   name);
+        assert_eq!(encode(ApiVersion::V1, &span),
+                   serde_json::to_vec(&vec![&value]).unwrap(),
+                   "{}",
+                   name);
+
+        let decoded: Span = serde_json::from_slice(&expected).unwrap();
+
+        assert_eq!(serde_json::to_value(&decoded).unwrap(), value, "{}", name);
+    }
+}
+
+#[test]
+fn v2_golden() {
+    for (name, span) in corpus::spans() {
+        let expected = golden(&format!("v2/{}.json", name));
+        let value: Value = serde_json::from_slice(&expected).unwrap();
+
+        validate("schema/zipkin2-api.json", &Value::Array(vec![value.clone()]), name);
+
+        assert_eq!(span.to_json_v2(), value, "{}", name);
+        assert_eq!(encode(ApiVersion::V2, &span),
+                   serde_json::to_vec(&vec![&value]).unwrap(),
+                   "{}",
+                   name);
+    }
+}
+
+#[test]
+fn schema() {
+    let schema: Value = serde_json::from_slice(&golden("schema/zipkin2-api.json")).unwrap();
+    let schema = JSONSchema::compile(&schema).unwrap();
+
+    for spans in &[r#"[{"traceId": "000000000000007b"}]"#,
+                   r#"[{"traceId": "000000000000007b", "id": "7b"}]"#,
+                   r#"[{"traceId": "000000000000007b", "id": "000000000000007b", "duration": 0}]"#,
+                   r#"[{"traceId": "000000000000007b", "id": "000000000000007b",
+                        "tags": {"http.status_code": 200}}]"#] {
+        assert!(!schema.is_valid(&serde_json::from_str(spans).unwrap()), "{}", spans);
+    }
+}
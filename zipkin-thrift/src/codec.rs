@@ -1,43 +1,187 @@
+use std::mem;
 use std::marker::PhantomData;
 
-use bytes::{BytesMut, BufMut};
+use byteorder::{BigEndian, ByteOrder};
+
+use bytes::BytesMut;
 
 use mime::Mime;
 
-use errors::Error;
-use encode::{ToThrift, to_writer};
+use thrift::{self, TransportErrorKind};
 
-use zipkin_core::{Encoder, MimeType};
+use errors::{Error, ErrorKind};
+use encode::{ToThrift, encode_with};
+use decode::{FromThrift, decode_with};
+use protocol::{Protocol, SharedBuffer};
 
-pub struct ThriftCodec<T, E>(PhantomData<(T, E)>);
+use zipkin_core::{Encoder, Decoder, MimeType, Limits, Truncate};
 
-unsafe impl<T, E> Send for ThriftCodec<T, E> {}
+pub struct ThriftCodec<T, E> {
+    /// The thrift protocol of the encoded spans
+    pub protocol: Protocol,
+    /// The size limits enforced before encoding
+    pub limits: Limits,
+    /// The allocation reused across the encodings
+    buf: Vec<u8>,
+    phantom: PhantomData<fn(T) -> E>,
+}
 
 impl<T, E> ThriftCodec<T, E> {
     pub fn new() -> Self {
-        ThriftCodec(PhantomData)
+        ThriftCodec {
+            protocol: Protocol::Binary,
+            limits: Limits::default(),
+            buf: Vec::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn compact() -> Self {
+        Self::new().with_protocol(Protocol::Compact)
+    }
+
+    pub fn with_protocol(self, protocol: Protocol) -> Self {
+        ThriftCodec { protocol: protocol, ..self }
+    }
+
+    pub fn with_limits(self, limits: Limits) -> Self {
+        ThriftCodec { limits: limits, ..self }
     }
 }
 
 impl<T, E> Encoder for ThriftCodec<T, E>
-    where T: ToThrift,
+    where T: ToThrift + Truncate,
           E: From<::std::io::Error> + From<::thrift::Error> + From<Error>
 {
     type Item = T;
     type Error = E;
 
-    fn encode(&mut self, item: Self::Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        let mut buf = dst.writer();
+    fn encode(&mut self, mut item: Self::Item, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        item.enforce_limits(&self.limits);
+
+        // the transport only lives in this call, so the codec could be sent to other threads
+        let buf = SharedBuffer::from(mem::replace(&mut self.buf, Vec::new()));
+        let result = encode_with(&buf,
+                                 self.protocol,
+                                 &item,
+                                 |bytes| dst.extend_from_slice(bytes));
+
+        self.buf = buf.into_inner();
 
-        to_writer(&mut buf, &item)?;
+        result?;
 
         Ok(())
     }
 }
 
 impl<T, E> MimeType for ThriftCodec<T, E> {
+    /// Zipkin only accepts `TBinaryProtocol` as `application/x-thrift`
     fn mime_type(&self) -> Mime {
-        mime!(Application / ("x-thrift"))
+        match self.protocol {
+            Protocol::Binary => mime!(Application / ("x-thrift")),
+            Protocol::Compact => mime!(Application / ("vnd.apache.thrift.compact")),
+        }
+    }
+}
+
+/// Decode the thrift structs, like a list of `core::Span` from the Kafka messages,
+/// or a list of `zipkin_core::Span` to feed the collectors.
+///
+/// The thrift structs carry no length, so an incomplete struct is parsed again from the start
+/// when more bytes arrive. The streams of large messages should be framed,
+/// like `TFramedTransport`, which only parses a frame once it was fully received.
+pub struct ThriftDecoder<T, E> {
+    /// The thrift protocol of the encoded spans
+    pub protocol: Protocol,
+    /// Each struct is prefixed with its length, as a 4 bytes big endian integer
+    pub framed: bool,
+    phantom: PhantomData<fn() -> (T, E)>,
+}
+
+impl<T, E> ThriftDecoder<T, E> {
+    pub fn new() -> Self {
+        ThriftDecoder {
+            protocol: Protocol::Binary,
+            framed: false,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn compact() -> Self {
+        Self::new().with_protocol(Protocol::Compact)
+    }
+
+    pub fn with_protocol(self, protocol: Protocol) -> Self {
+        ThriftDecoder { protocol: protocol, ..self }
+    }
+
+    pub fn with_framed(self, framed: bool) -> Self {
+        ThriftDecoder { framed: framed, ..self }
+    }
+}
+
+impl<T, E> ThriftDecoder<T, E>
+    where T: FromThrift,
+          E: From<::std::io::Error> + From<Error>
+{
+    fn decode_frame(&mut self, src: &mut BytesMut) -> Result<Option<T>, E> {
+        const LENGTH_SIZE: usize = 4;
+
+        if src.len() < LENGTH_SIZE {
+            return Ok(None);
+        }
+
+        let len = BigEndian::read_u32(&src[..LENGTH_SIZE]) as usize;
+
+        // check the frame length before parsing it
+        if src.len() < LENGTH_SIZE + len {
+            return Ok(None);
+        }
+
+        src.split_to(LENGTH_SIZE);
+
+        let frame = src.split_to(len).freeze();
+
+        decode_with(self.protocol, frame)
+            .0
+            .map(Some)
+            .map_err(|err| err.into())
+    }
+}
+
+impl<T, E> Decoder for ThriftDecoder<T, E>
+    where T: FromThrift,
+          E: From<::std::io::Error> + From<Error>
+{
+    type Item = T;
+    type Error = E;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if src.is_empty() {
+            return Ok(None);
+        }
+        if self.framed {
+            return self.decode_frame(src);
+        }
+
+        // decode from the taken bytes without copying them, and give back the rest
+        let (result, bytes, pos) = decode_with(self.protocol, src.take().freeze());
+
+        *src = bytes
+            .try_mut()
+            .unwrap_or_else(|bytes| BytesMut::from(&bytes[..]));
+
+        match result {
+            Ok(item) => {
+                src.split_to(pos);
+
+                Ok(Some(item))
+            }
+            // wait for the rest of the struct
+            Err(Error(ErrorKind::ThriftError(thrift::Error::Transport(ref err)), _))
+                if err.kind == TransportErrorKind::EndOfFile => Ok(None),
+            Err(err) => Err(err.into()),
+        }
     }
 }
 
@@ -45,11 +189,13 @@ impl<T, E> MimeType for ThriftCodec<T, E> {
 mod tests {
     use std::sync::Arc;
     use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-    use bytes::BytesMut;
+    use bytes::{BytesMut, BufMut};
 
     use zipkin_core::*;
 
     use super::*;
+    use core;
+    use encode::to_vec;
 
     #[test]
     fn encoder() {
@@ -58,12 +204,12 @@ mod tests {
                                lo: 123,
                                hi: Some(456),
                            })
-            .with_id(123)
-            .with_parent_id(456)
+            .with_id(SpanId(123))
+            .with_parent_id(SpanId(456))
             .with_debug(true);
         let endpoint =
             Some(Arc::new(Endpoint {
-                              name: Some("test"),
+                              name: Some("test".into()),
                               addr: Some(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
                                                          8080)),
                           }));
@@ -74,6 +220,105 @@ mod tests {
         let mut codec = ThriftCodec::<_, Error>::new();
         let mut buf = BytesMut::with_capacity(1024);
 
-        codec.encode(span, &mut buf).unwrap();
+        codec.encode(span.clone(), &mut buf).unwrap();
+
+        let mut encoded = buf.clone();
+        let mut decoder = ThriftDecoder::<core::Span, Error>::new();
+
+        assert_eq!(decoder.decode(&mut buf).unwrap(), Some(span.to_thrift()));
+        assert!(buf.is_empty());
+
+        // decode into the core model, which could be fed to the collectors
+        let decoded = ThriftDecoder::<Span, Error>::new()
+            .decode(&mut encoded)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(decoded.trace_id, span.trace_id);
+        assert_eq!(decoded.id, span.id);
+        assert_eq!(decoded.parent_id, span.parent_id);
+        assert_eq!(decoded.name, span.name);
+        assert_eq!(decoded.timestamp, span.timestamp);
+        assert_eq!(decoded.debug, Some(true));
+        assert_eq!(decoded.annotations[0].value, CLIENT_SEND);
+        assert_eq!(decoded.annotations[0].timestamp, span.annotations[0].timestamp);
+        assert_eq!(decoded.binary_annotations[0].key, HTTP_METHOD);
+        assert_eq!(decoded.binary_annotations[0].value,
+                   Value::String("GET".to_owned()));
+
+        let endpoint = decoded.binary_annotations[0].endpoint.as_ref().unwrap();
+
+        assert_eq!(endpoint.name, Some("test".into()));
+        assert_eq!(endpoint.addr,
+                   Some(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080)));
+    }
+
+    #[test]
+    fn framed() {
+        let span = Span::new("test");
+        let payload = to_vec(&span).unwrap();
+        let mut buf = BytesMut::with_capacity(1024);
+
+        buf.put_u32::<BigEndian>(payload.len() as u32);
+        buf.put_slice(&payload);
+
+        let mut frame = buf.split_to(10);
+        let mut decoder = ThriftDecoder::<core::Span, Error>::new().with_framed(true);
+
+        // the incomplete frame isn't parsed
+        assert_eq!(decoder.decode(&mut frame).unwrap(), None);
+        assert_eq!(frame.len(), 10);
+
+        frame.unsplit(buf);
+
+        assert_eq!(decoder.decode(&mut frame).unwrap(), Some(span.to_thrift()));
+        assert!(frame.is_empty());
+
+        // the truncated struct in a complete frame is an error
+        let mut frame = BytesMut::with_capacity(16);
+
+        frame.put_u32::<BigEndian>(4);
+        frame.put_slice(&payload[..4]);
+
+        assert!(decoder.decode(&mut frame).is_err());
+    }
+
+    #[test]
+    fn protocol() {
+        let mut span = Span::new("test");
+
+        span.annotate(CLIENT_SEND, None);
+        span.binary_annotate(HTTP_STATUS_CODE, 200i16, None);
+
+        let spans = vec![span.clone(), span];
+        let mut binary = BytesMut::with_capacity(1024);
+        let mut compact = BytesMut::with_capacity(1024);
+        let mut codec = ThriftCodec::<_, Error>::compact();
+
+        ThriftCodec::<_, Error>::new()
+            .encode(spans.clone(), &mut binary)
+            .unwrap();
+        codec.encode(spans.clone(), &mut compact).unwrap();
+
+        // the batch is framed as a single `list<Span>`, like the Zipkin collectors expect
+        assert_eq!(&binary[..], &to_vec(&spans).unwrap()[..]);
+        assert!(compact.len() < binary.len());
+
+        // the buffer is reused by the next encoding
+        codec.encode(spans.clone(), &mut compact).unwrap();
+
+        let mut decoder = ThriftDecoder::<Vec<core::Span>, Error>::compact();
+        let mut partial = compact.split_to(10);
+
+        assert_eq!(decoder.decode(&mut partial).unwrap(), None);
+        assert_eq!(partial.len(), 10);
+
+        partial.unsplit(compact);
+
+        let expected = Some(spans.to_thrift());
+
+        assert_eq!(decoder.decode(&mut partial).unwrap(), expected);
+        assert_eq!(decoder.decode(&mut partial).unwrap(), expected);
+        assert_eq!(decoder.decode(&mut partial).unwrap(), None);
     }
 }
\ No newline at end of file
@@ -0,0 +1,247 @@
+use std::cmp;
+use std::sync::Arc;
+use std::borrow::Cow;
+use std::net::{SocketAddr, Ipv4Addr, Ipv6Addr};
+
+use byteorder::{BigEndian, ByteOrder};
+
+use bytes::Bytes;
+
+use thrift::{self, ProtocolError, ProtocolErrorKind};
+use thrift::protocol::TInputProtocol;
+
+use zipkin_core as zipkin;
+
+use core;
+use errors::Result;
+use protocol::{Protocol, ReadBuffer};
+
+/// The most items allocated ahead of a list, the larger lists grow while they are read,
+/// so a forged list size can't exhaust the memory.
+const MAX_PREALLOCATED: usize = 1024;
+
+/// Decode the thrift structs from the protocol
+pub trait FromThrift: Sized {
+    fn read_from(proto: &mut TInputProtocol) -> thrift::Result<Self>;
+}
+
+impl FromThrift for core::Endpoint {
+    fn read_from(proto: &mut TInputProtocol) -> thrift::Result<Self> {
+        core::Endpoint::read_from_in_protocol(proto)
+    }
+}
+
+impl FromThrift for core::Annotation {
+    fn read_from(proto: &mut TInputProtocol) -> thrift::Result<Self> {
+        core::Annotation::read_from_in_protocol(proto)
+    }
+}
+
+impl FromThrift for core::BinaryAnnotation {
+    fn read_from(proto: &mut TInputProtocol) -> thrift::Result<Self> {
+        core::BinaryAnnotation::read_from_in_protocol(proto)
+    }
+}
+
+impl FromThrift for core::Span {
+    fn read_from(proto: &mut TInputProtocol) -> thrift::Result<Self> {
+        core::Span::read_from_in_protocol(proto)
+    }
+}
+
+fn invalid<T>(message: String) -> thrift::Result<T> {
+    Err(thrift::Error::Protocol(ProtocolError {
+                                    kind: ProtocolErrorKind::InvalidData,
+                                    message: message,
+                                }))
+}
+
+fn required<T>(field: Option<T>, name: &str) -> thrift::Result<T> {
+    match field {
+        Some(value) => Ok(value),
+        None => invalid(format!("missing required field `{}`", name)),
+    }
+}
+
+fn from_microseconds(us: i64) -> zipkin::Timestamp {
+    let (sec, us) = (us / 1000_000, us % 1000_000);
+
+    if us < 0 {
+        zipkin::timestamp(sec - 1, ((us + 1000_000) * 1000) as i32)
+    } else {
+        zipkin::timestamp(sec, (us * 1000) as i32)
+    }
+}
+
+/// The zero `ipv4` and `port`, and the empty `service_name` written by Zipkin are unknown.
+fn endpoint(host: Option<core::Endpoint>) -> Option<Arc<zipkin::Endpoint<'static>>> {
+    host.map(|host| {
+        let port = host.port.unwrap_or(0) as u16;
+        let addr = match (host.ipv4, host.ipv6) {
+            (Some(ipv4), _) if ipv4 != 0 => {
+                Some(SocketAddr::new(Ipv4Addr::from(ipv4 as u32).into(), port))
+            }
+            (_, Some(ref ipv6)) if ipv6.len() == 16 => {
+                let mut octets = [0u8; 16];
+
+                octets.copy_from_slice(ipv6);
+
+                Some(SocketAddr::new(Ipv6Addr::from(octets).into(), port))
+            }
+            _ => None,
+        };
+
+        Arc::new(zipkin::Endpoint {
+                     name: host.service_name
+                         .and_then(|name| if name.is_empty() {
+                                       None
+                                   } else {
+                                       Some(Cow::Owned(name))
+                                   }),
+                     addr: addr,
+                 })
+    })
+}
+
+fn annotation(annotation: core::Annotation) -> thrift::Result<zipkin::Annotation<'static>> {
+    Ok(zipkin::Annotation {
+           timestamp: from_microseconds(required(annotation.timestamp, "timestamp")?),
+           value: Cow::Owned(required(annotation.value, "value")?),
+           endpoint: endpoint(annotation.host),
+       })
+}
+
+fn value(ty: core::AnnotationType, v: Vec<u8>) -> thrift::Result<zipkin::Value<'static>> {
+    let expected = match ty {
+        core::AnnotationType::BOOL => Some(1),
+        core::AnnotationType::I16 => Some(2),
+        core::AnnotationType::I32 => Some(4),
+        core::AnnotationType::I64 |
+        core::AnnotationType::DOUBLE => Some(8),
+        core::AnnotationType::BYTES |
+        core::AnnotationType::STRING => None,
+    };
+
+    if expected.map_or(false, |len| len != v.len()) {
+        return invalid(format!("invalid {:?} value of {} bytes", ty, v.len()));
+    }
+
+    Ok(match ty {
+           core::AnnotationType::BOOL => zipkin::Value::Bool(v[0] != 0),
+           core::AnnotationType::BYTES => zipkin::Value::Blob(v),
+           core::AnnotationType::I16 => zipkin::Value::I16(BigEndian::read_i16(&v)),
+           core::AnnotationType::I32 => zipkin::Value::I32(BigEndian::read_i32(&v)),
+           core::AnnotationType::I64 => zipkin::Value::I64(BigEndian::read_i64(&v)),
+           core::AnnotationType::DOUBLE => zipkin::Value::Double(BigEndian::read_f64(&v)),
+           core::AnnotationType::STRING => {
+               match String::from_utf8(v) {
+                   Ok(s) => zipkin::Value::String(s),
+                   Err(err) => return invalid(format!("invalid STRING value, {}", err)),
+               }
+           }
+       })
+}
+
+fn binary_annotation(annotation: core::BinaryAnnotation)
+                     -> thrift::Result<zipkin::BinaryAnnotation<'static>> {
+    Ok(zipkin::BinaryAnnotation {
+           key: Cow::Owned(required(annotation.key, "key")?),
+           value: value(annotation.annotation_type
+                            .unwrap_or(core::AnnotationType::BYTES),
+                        required(annotation.value, "value")?)?,
+           endpoint: endpoint(annotation.host),
+       })
+}
+
+/// Decode the span into the core model, so it could be fed to the collectors.
+///
+/// The spans of the v1 model are kept as they were,
+/// the address annotations are not converted to the span kind and remote endpoint.
+impl FromThrift for zipkin::Span<'static> {
+    fn read_from(proto: &mut TInputProtocol) -> thrift::Result<Self> {
+        let span = core::Span::read_from_in_protocol(proto)?;
+
+        let trace_id = zipkin::TraceId {
+            lo: required(span.trace_id, "trace_id")? as u64,
+            hi: span.trace_id_high.map(|id| id as u64),
+        };
+        let id = zipkin::SpanId(required(span.id, "id")? as u64);
+        let name = Cow::Owned(required(span.name, "name")?);
+        let timestamp = from_microseconds(required(span.timestamp, "timestamp")?);
+
+        let mut decoded = zipkin::Span::from_parts(trace_id, id, name, timestamp);
+
+        decoded.parent_id = span.parent_id.map(|id| zipkin::SpanId(id as u64));
+        decoded.duration = span.duration.map(zipkin::Duration::microseconds);
+        decoded.annotations = span.annotations
+            .unwrap_or_default()
+            .into_iter()
+            .map(annotation)
+            .collect::<thrift::Result<_>>()?;
+        decoded.binary_annotations = span.binary_annotations
+            .unwrap_or_default()
+            .into_iter()
+            .map(binary_annotation)
+            .collect::<thrift::Result<_>>()?;
+        decoded.debug = span.debug;
+
+        Ok(decoded)
+    }
+}
+
+impl<T: FromThrift> FromThrift for Vec<T> {
+    fn read_from(proto: &mut TInputProtocol) -> thrift::Result<Self> {
+        let list = proto.read_list_begin()?;
+
+        if list.size < 0 {
+            return Err(thrift::Error::Protocol(ProtocolError {
+                                                   kind: ProtocolErrorKind::NegativeSize,
+                                                   message: format!("negative list size: {}",
+                                                                    list.size),
+                                               }));
+        }
+
+        let mut items = Vec::with_capacity(cmp::min(list.size as usize, MAX_PREALLOCATED));
+
+        for _ in 0..list.size {
+            items.push(T::read_from(proto)?);
+        }
+
+        proto.read_list_end()?;
+
+        Ok(items)
+    }
+}
+
+/// Decode the value from the bytes with the protocol, without copying them.
+///
+/// Returns the decoding result, and the bytes with the number of the consumed bytes.
+pub fn decode_with<T: FromThrift>(protocol: Protocol, src: Bytes) -> (Result<T>, Bytes, usize) {
+    let buf = ReadBuffer::new(src);
+    let value: Result<T> = {
+        let mut proto = protocol.input_protocol(buf.transport());
+
+        T::read_from(proto.as_mut()).map_err(|err| err.into())
+    };
+    let (bytes, pos) = buf.into_inner();
+
+    (value, bytes, pos)
+}
+
+pub fn from_slice<T: FromThrift>(buf: &[u8]) -> Result<T> {
+    decode_with(Protocol::Binary, Bytes::from(buf)).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_size() {
+        // the binary protocol writes the element type and the size of the list
+        assert!(from_slice::<Vec<core::Span>>(&[0x0c, 0xff, 0xff, 0xff, 0xff]).is_err());
+        assert!(from_slice::<Vec<core::Span>>(&[0x0c, 0x7f, 0xff, 0xff, 0xff]).is_err());
+        assert_eq!(from_slice::<Vec<core::Span>>(&[0x0c, 0x00, 0x00, 0x00, 0x00]).unwrap(),
+                   vec![]);
+    }
+}
@@ -1,22 +1,18 @@
-use std::ptr;
-use std::mem;
-use std::rc::Rc;
 use std::ops::Deref;
-use std::cell::RefCell;
 use std::io::prelude::*;
 use std::net::SocketAddr;
 
 use byteorder::{BigEndian, WriteBytesExt};
 
 use thrift;
-use thrift::protocol::{TListIdentifier, TType, TOutputProtocol, TBinaryOutputProtocol};
-use thrift::transport::{TBufferTransport, TPassThruTransport};
+use thrift::protocol::{TListIdentifier, TType, TOutputProtocol};
 
 use zipkin_core as zipkin;
 use zipkin_core::ToMicrosecond;
 
 use core;
 use errors::Result;
+use protocol::{Protocol, SharedBuffer};
 
 trait ToI64 {
     fn to_i64(&self) -> i64;
@@ -30,7 +26,7 @@ impl ToI64 for zipkin::Timestamp {
 
 impl ToI64 for zipkin::Duration {
     fn to_i64(&self) -> i64 {
-        self.num_milliseconds()
+        self.to_microseconds()
     }
 }
 
@@ -45,15 +41,15 @@ pub trait ToThrift {
 impl<'a> ToThrift for zipkin::Endpoint<'a> {
     type Output = core::Endpoint;
 
+    /// Like Zipkin, always write the `ipv4`, `port` and `service_name` fields,
+    /// with zero or empty values when unknown.
     fn to_thrift(&self) -> Self::Output {
         core::Endpoint {
-            service_name: self.name.map(|name| name.into()),
+            service_name: Some(self.name.as_ref().map_or("", |name| &**name).into()),
             ipv4: if let Some(SocketAddr::V4(addr)) = self.addr {
-                let ip = &addr.ip().octets()[..];
-
-                Some(unsafe { ptr::read(ip.as_ptr() as *const i32) })
+                Some(u32::from(*addr.ip()) as i32)
             } else {
-                None
+                Some(0)
             },
             ipv6: if let Some(SocketAddr::V6(addr)) = self.addr {
                 let ip = &addr.ip().octets()[..];
@@ -62,7 +58,7 @@ impl<'a> ToThrift for zipkin::Endpoint<'a> {
             } else {
                 None
             },
-            port: self.addr.map(|addr| addr.port() as i16),
+            port: Some(self.addr.map_or(0, |addr| addr.port() as i16)),
         }
     }
 
@@ -77,7 +73,7 @@ impl<'a> ToThrift for zipkin::Annotation<'a> {
     fn to_thrift(&self) -> Self::Output {
         core::Annotation {
             timestamp: Some(self.timestamp.to_i64()),
-            value: Some(self.value.into()),
+            value: Some(self.value.as_ref().into()),
             host: self.endpoint.to_thrift(),
         }
     }
@@ -95,6 +91,7 @@ impl<'a> ToThrift for zipkin::BinaryAnnotation<'a> {
         let (value, ty) = match self.value {
             zipkin::Value::Bool(v) => (vec![if v { 1 } else { 0 }], core::AnnotationType::BOOL),
             zipkin::Value::Bytes(v) => (v.into(), core::AnnotationType::BYTES),
+            zipkin::Value::Blob(ref v) => (v.clone(), core::AnnotationType::BYTES),
             zipkin::Value::I16(v) => {
                 buf.write_i16::<BigEndian>(v).unwrap();
 
@@ -111,9 +108,7 @@ impl<'a> ToThrift for zipkin::BinaryAnnotation<'a> {
                 (buf, core::AnnotationType::I64)
             }
             zipkin::Value::Double(v) => {
-                let v: [u8; 8] = unsafe { mem::transmute(v) };
-
-                buf.write(&v).unwrap();
+                buf.write_f64::<BigEndian>(v).unwrap();
 
                 (buf, core::AnnotationType::DOUBLE)
             }
@@ -122,7 +117,7 @@ impl<'a> ToThrift for zipkin::BinaryAnnotation<'a> {
         };
 
         core::BinaryAnnotation {
-            key: Some(self.key.into()),
+            key: Some(self.key.as_ref().into()),
             value: Some(value),
             annotation_type: Some(ty),
             host: self.endpoint.to_thrift(),
@@ -141,11 +136,20 @@ impl<'a> ToThrift for zipkin::Span<'a> {
         core::Span {
             trace_id: Some(self.trace_id.lo as i64),
             trace_id_high: self.trace_id.hi.map(|id| id as i64),
-            name: Some(self.name.into()),
-            id: Some(self.id as i64),
-            parent_id: self.parent_id.map(|id| id as i64),
-            annotations: self.annotations.as_slice().to_thrift(),
-            binary_annotations: self.binary_annotations.as_slice().to_thrift(),
+            name: Some(self.name.as_ref().into()),
+            id: Some(self.id.0 as i64),
+            parent_id: self.parent_id.map(|id| id.0 as i64),
+            annotations: Some(self.annotations.as_slice().to_thrift()),
+            binary_annotations: match self.remote_address() {
+                Some(address) => {
+                    Some(self.binary_annotations
+                             .iter()
+                             .chain(Some(&address))
+                             .map(|annotation| annotation.to_thrift())
+                             .collect())
+                }
+                None => Some(self.binary_annotations.as_slice().to_thrift()),
+            },
             debug: self.debug,
             timestamp: Some(self.timestamp.to_i64()),
             duration: self.duration.map(|d| d.to_i64()),
@@ -174,16 +178,10 @@ impl<'a, T: ToThrift, D: Deref<Target = T>> ToThrift for Option<D> {
 }
 
 impl<'a, T: ToThrift> ToThrift for &'a [T] {
-    type Output = Option<Vec<T::Output>>;
+    type Output = Vec<T::Output>;
 
     fn to_thrift(&self) -> Self::Output {
-        if self.is_empty() {
-            None
-        } else {
-            Some(self.iter()
-                     .map(|item| item.to_thrift())
-                     .collect::<Vec<T::Output>>())
-        }
+        self.iter().map(|item| item.to_thrift()).collect()
     }
 
     fn write_to(&self, proto: &mut TOutputProtocol) -> thrift::Result<()> {
@@ -199,30 +197,16 @@ impl<'a, T: ToThrift> ToThrift for &'a [T] {
 }
 
 
+/// The `list<Span>` message, consumed by the Zipkin Kafka and HTTP collectors.
 impl<T: ToThrift> ToThrift for Vec<T> {
-    type Output = Option<Vec<T::Output>>;
+    type Output = Vec<T::Output>;
 
     fn to_thrift(&self) -> Self::Output {
-        let s = self.as_slice();
-        if s.is_empty() {
-            None
-        } else {
-            Some(s.iter()
-                     .map(|item| item.to_thrift())
-                     .collect::<Vec<T::Output>>())
-        }
+        self.as_slice().to_thrift()
     }
 
     fn write_to(&self, proto: &mut TOutputProtocol) -> thrift::Result<()> {
-        let s = self.as_slice();
-        proto
-            .write_list_begin(&TListIdentifier::new(TType::Struct, s.len() as i32))?;
-
-        for item in s.iter() {
-            item.write_to(proto)?;
-        }
-
-        proto.write_list_end()
+        self.as_slice().write_to(proto)
     }
 }
 
@@ -230,34 +214,34 @@ pub fn to_thrift<T: ToThrift>(value: &T) -> T::Output {
     value.to_thrift()
 }
 
-pub fn to_vec<T: ToThrift>(value: &T) -> Result<Vec<u8>> {
-    let buf = Rc::new(RefCell::new(Box::new(TBufferTransport::with_capacity(0, 4096))));
-    let mut proto =
-        TBinaryOutputProtocol::new(Rc::new(RefCell::new(Box::new(TPassThruTransport {
-                                                                     inner: buf.clone(),
-                                                                 }))),
-                                   true);
+/// Encode the value to the buffer with the protocol, and drain the encoded bytes.
+pub fn encode_with<T, F, R>(buf: &SharedBuffer, protocol: Protocol, value: &T, f: F) -> Result<R>
+    where T: ToThrift,
+          F: FnOnce(&[u8]) -> R
+{
+    let mut proto = protocol.output_protocol(buf.transport());
 
-    value.write_to(&mut proto)?;
+    if let Err(err) = value.write_to(proto.as_mut()).and_then(|_| proto.flush()) {
+        buf.drain(|_| ());
 
-    let bytes = buf.borrow_mut().write_buffer_to_vec();
+        return Err(err.into());
+    }
 
-    Ok(bytes)
+    Ok(buf.drain(f))
 }
 
-pub fn to_writer<W: ?Sized + Write, T: ToThrift>(writer: &mut W, value: &T) -> Result<usize> {
-    let buf = Rc::new(RefCell::new(Box::new(TBufferTransport::with_capacity(0, 4096))));
-    let mut proto =
-        TBinaryOutputProtocol::new(Rc::new(RefCell::new(Box::new(TPassThruTransport {
-                                                                     inner: buf.clone(),
-                                                                 }))),
-                                   true);
-
-    value.write_to(&mut proto)?;
-
-    let wrote = writer.write(buf.borrow().write_buffer_as_ref())?;
+pub fn to_vec<T: ToThrift>(value: &T) -> Result<Vec<u8>> {
+    encode_with(&SharedBuffer::new(),
+                Protocol::Binary,
+                value,
+                |bytes| bytes.to_vec())
+}
 
-    Ok(wrote)
+pub fn to_writer<W: ?Sized + Write, T: ToThrift>(writer: &mut W, value: &T) -> Result<usize> {
+    encode_with(&SharedBuffer::new(), Protocol::Binary, value, |bytes| {
+        writer.write_all(bytes).map(|_| bytes.len())
+    })?
+        .map_err(|err| err.into())
 }
 
 #[cfg(test)]
@@ -269,6 +253,7 @@ mod tests {
 
     use super::*;
     use super::super::core;
+    use decode::from_slice;
 
     #[test]
     fn to_thrift() {
@@ -277,12 +262,12 @@ mod tests {
                                lo: 123,
                                hi: Some(456),
                            })
-            .with_id(123)
-            .with_parent_id(456)
+            .with_id(SpanId(123))
+            .with_parent_id(SpanId(456))
             .with_debug(true);
         let endpoint =
             Some(Arc::new(Endpoint {
-                              name: Some("test"),
+                              name: Some("test".into()),
                               addr: Some(SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
                                                          8080)),
                           }));
@@ -355,4 +340,447 @@ mod tests {
 
         assert_eq!(bytes.len(), 450);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn remote_address() {
+        let mut span = Span::new("test");
+
+        span.binary_annotate(HTTP_METHOD, "GET", None);
+        span.set_remote_endpoint(Kind::Client,
+                                 Endpoint {
+                                     name: Some("backend".into()),
+                                     addr: None,
+                                 });
+
+        let annotations = span.to_thrift().binary_annotations.unwrap();
+
+        assert_eq!(annotations.len(), 2);
+        assert_eq!(annotations[1].key.as_ref().unwrap(), SERVER_ADDR);
+        assert_eq!(annotations[1].value.as_ref().unwrap(), &vec![1]);
+        assert_eq!(annotations[1].annotation_type.unwrap(),
+                   core::AnnotationType::BOOL);
+        assert_eq!(annotations[1]
+                       .host
+                       .as_ref()
+                       .unwrap()
+                       .service_name
+                       .as_ref()
+                       .unwrap(),
+                   "backend");
+    }
+
+    #[test]
+    fn client_span() {
+        let frontend = Arc::new(Endpoint {
+                                    name: Some("frontend".into()),
+                                    addr: Some("172.17.0.13:0".parse().unwrap()),
+                                });
+        let mut span = Span::new("get")
+            .with_trace_id(TraceId {
+                               lo: 0x48485a3953bb6124,
+                               hi: Some(0x463ac35c9f6413ad),
+                           })
+            .with_id(SpanId(0x5b4185666d50f68b))
+            .with_parent_id(SpanId(0x6b221d5bc9e6496c))
+            .with_local_endpoint(frontend);
+
+        span.timestamp = timestamp(1472470996, 199000000);
+        span.duration = Some(Duration::microseconds(207000));
+
+        for &(value, nsec) in &[(CLIENT_SEND, 199000000), (CLIENT_RECV, 406000000)] {
+            span.annotate(value, None);
+            span.annotations.last_mut().unwrap().timestamp = timestamp(1472470996, nsec);
+        }
+
+        span.binary_annotate("clnt/finagle.version", "6.45.0", None);
+        span.binary_annotate(HTTP_PATH, "/api", None);
+        span.set_remote_endpoint(Kind::Client,
+                                 Endpoint {
+                                     name: Some("backend".into()),
+                                     addr: Some("192.168.99.101:9000".parse().unwrap()),
+                                 });
+
+        let spans = vec![span];
+
+        assert_eq!(to_vec(&spans).unwrap(), CLIENT_SPAN);
+        assert_eq!(from_slice::<Vec<core::Span>>(CLIENT_SPAN).unwrap(),
+                   spans.to_thrift());
+    }
+
+    #[test]
+    fn server_spans() {
+        let api = Arc::new(Endpoint {
+                               name: Some("api".into()),
+                               addr: Some("[2001:db8::c001]:443".parse().unwrap()),
+                           });
+        let trace_id = TraceId {
+            lo: 0x7a6b5c4d3e2f1a0b,
+            hi: None,
+        };
+        let mut root = Span::new("ünïcødé")
+            .with_trace_id(trace_id.clone())
+            .with_id(SpanId(0x7a6b5c4d3e2f1a0b))
+            .with_local_endpoint(api)
+            .with_debug(true);
+
+        root.timestamp = timestamp(1472470996, 0);
+        root.duration = Some(Duration::microseconds(1));
+
+        root.annotate(SERVER_RECV, None);
+        root.annotate(SERVER_SEND, None);
+        root.annotations[0].timestamp = timestamp(1472470996, 0);
+        root.annotations[1].timestamp = timestamp(1472470996, 1000);
+
+        root.binary_annotate("bool", false, None);
+        root.binary_annotate("i16", -2i16, None);
+        root.binary_annotate("i32", 65536i32, None);
+        root.binary_annotate("i64", -1i64, None);
+        root.binary_annotate("double", 123.456, None);
+        root.binary_annotate("bytes", &b"\x00\xff"[..], None);
+
+        let mut child = Span::new("child")
+            .with_trace_id(trace_id)
+            .with_id(SpanId(1))
+            .with_parent_id(root.id);
+
+        child.timestamp = timestamp(1472470996, 500000);
+        child.annotate(LOCAL_COMPONENT, None);
+        child.annotations[0].timestamp = timestamp(1472470996, 500000);
+
+        let spans = vec![root, child];
+
+        assert_eq!(to_vec(&spans).unwrap(), SERVER_SPANS);
+        assert_eq!(from_slice::<Vec<core::Span>>(SERVER_SPANS).unwrap(),
+                   spans.to_thrift());
+    }
+
+    // The reference payloads were written by hand in the `list<Span>` layout that Zipkin's
+    // `SpanBytesEncoder.THRIFT` writes with `TBinaryProtocol`. They were not captured from
+    // Zipkin itself. See `testdata/corpus.rs` for how to check them against the Java encoder.
+
+    const CLIENT_SPAN: &'static [u8] = &[
+        0x0c, 0x00, 0x00, 0x00, 0x01, // list<Span>
+            0x0a, 0x00, 0x01, // trace_id
+            0x48, 0x48, 0x5a, 0x39, 0x53, 0xbb, 0x61, 0x24, // 0x48485a3953bb6124
+            0x0b, 0x00, 0x03, // name
+            0x00, 0x00, 0x00, 0x03, // name length
+            0x67, 0x65, 0x74, // 'get'
+            0x0a, 0x00, 0x04, // id
+            0x5b, 0x41, 0x85, 0x66, 0x6d, 0x50, 0xf6, 0x8b, // 0x5b4185666d50f68b
+            0x0a, 0x00, 0x05, // parent_id
+            0x6b, 0x22, 0x1d, 0x5b, 0xc9, 0xe6, 0x49, 0x6c, // 0x6b221d5bc9e6496c
+            0x0f, 0x00, 0x06, // annotations
+            0x0c, 0x00, 0x00, 0x00, 0x02, // list<Annotation>
+                0x0a, 0x00, 0x01, // timestamp
+                0x00, 0x05, 0x3b, 0x34, 0x5e, 0xab, 0xa6, 0x58, // 1472470996199000
+                0x0b, 0x00, 0x02, // value
+                0x00, 0x00, 0x00, 0x02, // value length
+                0x63, 0x73, // 'cs'
+                0x0c, 0x00, 0x03, // host
+                    0x08, 0x00, 0x01, // ipv4
+                    0xac, 0x11, 0x00, 0x0d, // 172.17.0.13
+                    0x06, 0x00, 0x02, // port
+                    0x00, 0x00, // 0
+                    0x0b, 0x00, 0x03, // service_name
+                    0x00, 0x00, 0x00, 0x08, // service_name length
+                    0x66, 0x72, 0x6f, 0x6e, 0x74, 0x65, 0x6e, 0x64, // 'frontend'
+                    0x00, // endpoint stop
+                0x00, // annotation stop
+                0x0a, 0x00, 0x01, // timestamp
+                0x00, 0x05, 0x3b, 0x34, 0x5e, 0xae, 0xce, 0xf0, // 1472470996406000
+                0x0b, 0x00, 0x02, // value
+                0x00, 0x00, 0x00, 0x02, // value length
+                0x63, 0x72, // 'cr'
+                0x0c, 0x00, 0x03, // host
+                    0x08, 0x00, 0x01, // ipv4
+                    0xac, 0x11, 0x00, 0x0d, // 172.17.0.13
+                    0x06, 0x00, 0x02, // port
+                    0x00, 0x00, // 0
+                    0x0b, 0x00, 0x03, // service_name
+                    0x00, 0x00, 0x00, 0x08, // service_name length
+                    0x66, 0x72, 0x6f, 0x6e, 0x74, 0x65, 0x6e, 0x64, // 'frontend'
+                    0x00, // endpoint stop
+                0x00, // annotation stop
+            0x0f, 0x00, 0x08, // binary_annotations
+            0x0c, 0x00, 0x00, 0x00, 0x03, // list<BinaryAnnotation>
+                0x0b, 0x00, 0x01, // key
+                0x00, 0x00, 0x00, 0x14, // key length
+                0x63, 0x6c, 0x6e, 0x74, 0x2f, 0x66, 0x69, 0x6e, // 'clnt/finagle.version'
+                0x61, 0x67, 0x6c, 0x65, 0x2e, 0x76, 0x65, 0x72,
+                0x73, 0x69, 0x6f, 0x6e,
+                0x0b, 0x00, 0x02, // value
+                0x00, 0x00, 0x00, 0x06, // value length
+                0x36, 0x2e, 0x34, 0x35, 0x2e, 0x30, // STRING '6.45.0'
+                0x08, 0x00, 0x03, // annotation_type
+                0x00, 0x00, 0x00, 0x06, // STRING
+                0x0c, 0x00, 0x04, // host
+                    0x08, 0x00, 0x01, // ipv4
+                    0xac, 0x11, 0x00, 0x0d, // 172.17.0.13
+                    0x06, 0x00, 0x02, // port
+                    0x00, 0x00, // 0
+                    0x0b, 0x00, 0x03, // service_name
+                    0x00, 0x00, 0x00, 0x08, // service_name length
+                    0x66, 0x72, 0x6f, 0x6e, 0x74, 0x65, 0x6e, 0x64, // 'frontend'
+                    0x00, // endpoint stop
+                0x00, // binary annotation stop
+                0x0b, 0x00, 0x01, // key
+                0x00, 0x00, 0x00, 0x09, // key length
+                0x68, 0x74, 0x74, 0x70, 0x2e, 0x70, 0x61, 0x74, // 'http.path'
+                0x68,
+                0x0b, 0x00, 0x02, // value
+                0x00, 0x00, 0x00, 0x04, // value length
+                0x2f, 0x61, 0x70, 0x69, // STRING '/api'
+                0x08, 0x00, 0x03, // annotation_type
+                0x00, 0x00, 0x00, 0x06, // STRING
+                0x0c, 0x00, 0x04, // host
+                    0x08, 0x00, 0x01, // ipv4
+                    0xac, 0x11, 0x00, 0x0d, // 172.17.0.13
+                    0x06, 0x00, 0x02, // port
+                    0x00, 0x00, // 0
+                    0x0b, 0x00, 0x03, // service_name
+                    0x00, 0x00, 0x00, 0x08, // service_name length
+                    0x66, 0x72, 0x6f, 0x6e, 0x74, 0x65, 0x6e, 0x64, // 'frontend'
+                    0x00, // endpoint stop
+                0x00, // binary annotation stop
+                0x0b, 0x00, 0x01, // key
+                0x00, 0x00, 0x00, 0x02, // key length
+                0x73, 0x61, // 'sa'
+                0x0b, 0x00, 0x02, // value
+                0x00, 0x00, 0x00, 0x01, // value length
+                0x01, // BOOL True
+                0x08, 0x00, 0x03, // annotation_type
+                0x00, 0x00, 0x00, 0x00, // BOOL
+                0x0c, 0x00, 0x04, // host
+                    0x08, 0x00, 0x01, // ipv4
+                    0xc0, 0xa8, 0x63, 0x65, // 192.168.99.101
+                    0x06, 0x00, 0x02, // port
+                    0x23, 0x28, // 9000
+                    0x0b, 0x00, 0x03, // service_name
+                    0x00, 0x00, 0x00, 0x07, // service_name length
+                    0x62, 0x61, 0x63, 0x6b, 0x65, 0x6e, 0x64, // 'backend'
+                    0x00, // endpoint stop
+                0x00, // binary annotation stop
+            0x0a, 0x00, 0x0a, // timestamp
+            0x00, 0x05, 0x3b, 0x34, 0x5e, 0xab, 0xa6, 0x58, // 1472470996199000
+            0x0a, 0x00, 0x0b, // duration
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0x28, 0x98, // 207000
+            0x0a, 0x00, 0x0c, // trace_id_high
+            0x46, 0x3a, 0xc3, 0x5c, 0x9f, 0x64, 0x13, 0xad, // 0x463ac35c9f6413ad
+            0x00, // span stop
+    ];
+
+    const SERVER_SPANS: &'static [u8] = &[
+        0x0c, 0x00, 0x00, 0x00, 0x02, // list<Span>
+            0x0a, 0x00, 0x01, // trace_id
+            0x7a, 0x6b, 0x5c, 0x4d, 0x3e, 0x2f, 0x1a, 0x0b, // 0x7a6b5c4d3e2f1a0b
+            0x0b, 0x00, 0x03, // name
+            0x00, 0x00, 0x00, 0x0b, // name length
+            0xc3, 0xbc, 0x6e, 0xc3, 0xaf, 0x63, 0xc3, 0xb8, // 'ünïcødé'
+            0x64, 0xc3, 0xa9,
+            0x0a, 0x00, 0x04, // id
+            0x7a, 0x6b, 0x5c, 0x4d, 0x3e, 0x2f, 0x1a, 0x0b, // 0x7a6b5c4d3e2f1a0b
+            0x0f, 0x00, 0x06, // annotations
+            0x0c, 0x00, 0x00, 0x00, 0x02, // list<Annotation>
+                0x0a, 0x00, 0x01, // timestamp
+                0x00, 0x05, 0x3b, 0x34, 0x5e, 0xa8, 0x9d, 0x00, // 1472470996000000
+                0x0b, 0x00, 0x02, // value
+                0x00, 0x00, 0x00, 0x02, // value length
+                0x73, 0x72, // 'sr'
+                0x0c, 0x00, 0x03, // host
+                    0x08, 0x00, 0x01, // ipv4
+                    0x00, 0x00, 0x00, 0x00, // 0
+                    0x06, 0x00, 0x02, // port
+                    0x01, 0xbb, // 443
+                    0x0b, 0x00, 0x03, // service_name
+                    0x00, 0x00, 0x00, 0x03, // service_name length
+                    0x61, 0x70, 0x69, // 'api'
+                    0x0b, 0x00, 0x04, // ipv6
+                    0x00, 0x00, 0x00, 0x10, // ipv6 length
+                    0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00, // 2001:db8::c001
+                    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc0, 0x01,
+                    0x00, // endpoint stop
+                0x00, // annotation stop
+                0x0a, 0x00, 0x01, // timestamp
+                0x00, 0x05, 0x3b, 0x34, 0x5e, 0xa8, 0x9d, 0x01, // 1472470996000001
+                0x0b, 0x00, 0x02, // value
+                0x00, 0x00, 0x00, 0x02, // value length
+                0x73, 0x73, // 'ss'
+                0x0c, 0x00, 0x03, // host
+                    0x08, 0x00, 0x01, // ipv4
+                    0x00, 0x00, 0x00, 0x00, // 0
+                    0x06, 0x00, 0x02, // port
+                    0x01, 0xbb, // 443
+                    0x0b, 0x00, 0x03, // service_name
+                    0x00, 0x00, 0x00, 0x03, // service_name length
+                    0x61, 0x70, 0x69, // 'api'
+                    0x0b, 0x00, 0x04, // ipv6
+                    0x00, 0x00, 0x00, 0x10, // ipv6 length
+                    0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00, // 2001:db8::c001
+                    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc0, 0x01,
+                    0x00, // endpoint stop
+                0x00, // annotation stop
+            0x0f, 0x00, 0x08, // binary_annotations
+            0x0c, 0x00, 0x00, 0x00, 0x06, // list<BinaryAnnotation>
+                0x0b, 0x00, 0x01, // key
+                0x00, 0x00, 0x00, 0x04, // key length
+                0x62, 0x6f, 0x6f, 0x6c, // 'bool'
+                0x0b, 0x00, 0x02, // value
+                0x00, 0x00, 0x00, 0x01, // value length
+                0x00, // BOOL False
+                0x08, 0x00, 0x03, // annotation_type
+                0x00, 0x00, 0x00, 0x00, // BOOL
+                0x0c, 0x00, 0x04, // host
+                    0x08, 0x00, 0x01, // ipv4
+                    0x00, 0x00, 0x00, 0x00, // 0
+                    0x06, 0x00, 0x02, // port
+                    0x01, 0xbb, // 443
+                    0x0b, 0x00, 0x03, // service_name
+                    0x00, 0x00, 0x00, 0x03, // service_name length
+                    0x61, 0x70, 0x69, // 'api'
+                    0x0b, 0x00, 0x04, // ipv6
+                    0x00, 0x00, 0x00, 0x10, // ipv6 length
+                    0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00, // 2001:db8::c001
+                    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc0, 0x01,
+                    0x00, // endpoint stop
+                0x00, // binary annotation stop
+                0x0b, 0x00, 0x01, // key
+                0x00, 0x00, 0x00, 0x03, // key length
+                0x69, 0x31, 0x36, // 'i16'
+                0x0b, 0x00, 0x02, // value
+                0x00, 0x00, 0x00, 0x02, // value length
+                0xff, 0xfe, // I16 -2
+                0x08, 0x00, 0x03, // annotation_type
+                0x00, 0x00, 0x00, 0x02, // I16
+                0x0c, 0x00, 0x04, // host
+                    0x08, 0x00, 0x01, // ipv4
+                    0x00, 0x00, 0x00, 0x00, // 0
+                    0x06, 0x00, 0x02, // port
+                    0x01, 0xbb, // 443
+                    0x0b, 0x00, 0x03, // service_name
+                    0x00, 0x00, 0x00, 0x03, // service_name length
+                    0x61, 0x70, 0x69, // 'api'
+                    0x0b, 0x00, 0x04, // ipv6
+                    0x00, 0x00, 0x00, 0x10, // ipv6 length
+                    0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00, // 2001:db8::c001
+                    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc0, 0x01,
+                    0x00, // endpoint stop
+                0x00, // binary annotation stop
+                0x0b, 0x00, 0x01, // key
+                0x00, 0x00, 0x00, 0x03, // key length
+                0x69, 0x33, 0x32, // 'i32'
+                0x0b, 0x00, 0x02, // value
+                0x00, 0x00, 0x00, 0x04, // value length
+                0x00, 0x01, 0x00, 0x00, // I32 65536
+                0x08, 0x00, 0x03, // annotation_type
+                0x00, 0x00, 0x00, 0x03, // I32
+                0x0c, 0x00, 0x04, // host
+                    0x08, 0x00, 0x01, // ipv4
+                    0x00, 0x00, 0x00, 0x00, // 0
+                    0x06, 0x00, 0x02, // port
+                    0x01, 0xbb, // 443
+                    0x0b, 0x00, 0x03, // service_name
+                    0x00, 0x00, 0x00, 0x03, // service_name length
+                    0x61, 0x70, 0x69, // 'api'
+                    0x0b, 0x00, 0x04, // ipv6
+                    0x00, 0x00, 0x00, 0x10, // ipv6 length
+                    0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00, // 2001:db8::c001
+                    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc0, 0x01,
+                    0x00, // endpoint stop
+                0x00, // binary annotation stop
+                0x0b, 0x00, 0x01, // key
+                0x00, 0x00, 0x00, 0x03, // key length
+                0x69, 0x36, 0x34, // 'i64'
+                0x0b, 0x00, 0x02, // value
+                0x00, 0x00, 0x00, 0x08, // value length
+                0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // I64 -1
+                0x08, 0x00, 0x03, // annotation_type
+                0x00, 0x00, 0x00, 0x04, // I64
+                0x0c, 0x00, 0x04, // host
+                    0x08, 0x00, 0x01, // ipv4
+                    0x00, 0x00, 0x00, 0x00, // 0
+                    0x06, 0x00, 0x02, // port
+                    0x01, 0xbb, // 443
+                    0x0b, 0x00, 0x03, // service_name
+                    0x00, 0x00, 0x00, 0x03, // service_name length
+                    0x61, 0x70, 0x69, // 'api'
+                    0x0b, 0x00, 0x04, // ipv6
+                    0x00, 0x00, 0x00, 0x10, // ipv6 length
+                    0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00, // 2001:db8::c001
+                    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc0, 0x01,
+                    0x00, // endpoint stop
+                0x00, // binary annotation stop
+                0x0b, 0x00, 0x01, // key
+                0x00, 0x00, 0x00, 0x06, // key length
+                0x64, 0x6f, 0x75, 0x62, 0x6c, 0x65, // 'double'
+                0x0b, 0x00, 0x02, // value
+                0x00, 0x00, 0x00, 0x08, // value length
+                0x40, 0x5e, 0xdd, 0x2f, 0x1a, 0x9f, 0xbe, 0x77, // DOUBLE 123.456
+                0x08, 0x00, 0x03, // annotation_type
+                0x00, 0x00, 0x00, 0x05, // DOUBLE
+                0x0c, 0x00, 0x04, // host
+                    0x08, 0x00, 0x01, // ipv4
+                    0x00, 0x00, 0x00, 0x00, // 0
+                    0x06, 0x00, 0x02, // port
+                    0x01, 0xbb, // 443
+                    0x0b, 0x00, 0x03, // service_name
+                    0x00, 0x00, 0x00, 0x03, // service_name length
+                    0x61, 0x70, 0x69, // 'api'
+                    0x0b, 0x00, 0x04, // ipv6
+                    0x00, 0x00, 0x00, 0x10, // ipv6 length
+                    0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00, // 2001:db8::c001
+                    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc0, 0x01,
+                    0x00, // endpoint stop
+                0x00, // binary annotation stop
+                0x0b, 0x00, 0x01, // key
+                0x00, 0x00, 0x00, 0x05, // key length
+                0x62, 0x79, 0x74, 0x65, 0x73, // 'bytes'
+                0x0b, 0x00, 0x02, // value
+                0x00, 0x00, 0x00, 0x02, // value length
+                0x00, 0xff, // BYTES b'\x00\xff'
+                0x08, 0x00, 0x03, // annotation_type
+                0x00, 0x00, 0x00, 0x01, // BYTES
+                0x0c, 0x00, 0x04, // host
+                    0x08, 0x00, 0x01, // ipv4
+                    0x00, 0x00, 0x00, 0x00, // 0
+                    0x06, 0x00, 0x02, // port
+                    0x01, 0xbb, // 443
+                    0x0b, 0x00, 0x03, // service_name
+                    0x00, 0x00, 0x00, 0x03, // service_name length
+                    0x61, 0x70, 0x69, // 'api'
+                    0x0b, 0x00, 0x04, // ipv6
+                    0x00, 0x00, 0x00, 0x10, // ipv6 length
+                    0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00, // 2001:db8::c001
+                    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc0, 0x01,
+                    0x00, // endpoint stop
+                0x00, // binary annotation stop
+            0x02, 0x00, 0x09, // debug
+            0x01, // true
+            0x0a, 0x00, 0x0a, // timestamp
+            0x00, 0x05, 0x3b, 0x34, 0x5e, 0xa8, 0x9d, 0x00, // 1472470996000000
+            0x0a, 0x00, 0x0b, // duration
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, // 1
+            0x00, // span stop
+            0x0a, 0x00, 0x01, // trace_id
+            0x7a, 0x6b, 0x5c, 0x4d, 0x3e, 0x2f, 0x1a, 0x0b, // 0x7a6b5c4d3e2f1a0b
+            0x0b, 0x00, 0x03, // name
+            0x00, 0x00, 0x00, 0x05, // name length
+            0x63, 0x68, 0x69, 0x6c, 0x64, // 'child'
+            0x0a, 0x00, 0x04, // id
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, // 0x1
+            0x0a, 0x00, 0x05, // parent_id
+            0x7a, 0x6b, 0x5c, 0x4d, 0x3e, 0x2f, 0x1a, 0x0b, // 0x7a6b5c4d3e2f1a0b
+            0x0f, 0x00, 0x06, // annotations
+            0x0c, 0x00, 0x00, 0x00, 0x01, // list<Annotation>
+                0x0a, 0x00, 0x01, // timestamp
+                0x00, 0x05, 0x3b, 0x34, 0x5e, 0xa8, 0x9e, 0xf4, // 1472470996000500
+                0x0b, 0x00, 0x02, // value
+                0x00, 0x00, 0x00, 0x02, // value length
+                0x6c, 0x63, // 'lc'
+                0x00, // annotation stop
+            0x0f, 0x00, 0x08, // binary_annotations
+            0x0c, 0x00, 0x00, 0x00, 0x00, // list<BinaryAnnotation>
+            0x0a, 0x00, 0x0a, // timestamp
+            0x00, 0x05, 0x3b, 0x34, 0x5e, 0xa8, 0x9e, 0xf4, // 1472470996000500
+            0x00, // span stop
+    ];
+}
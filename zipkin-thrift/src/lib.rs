@@ -12,10 +12,14 @@ extern crate zipkin_core;
 
 pub use thrift::Error as ThriftError;
 
-mod core;
+pub mod core;
 pub mod errors;
+mod protocol;
 mod encode;
+mod decode;
 mod codec;
 
+pub use protocol::Protocol;
 pub use encode::{ToThrift, to_thrift, to_vec, to_writer};
-pub use codec::ThriftCodec;
+pub use decode::{FromThrift, from_slice};
+pub use codec::{ThriftCodec, ThriftDecoder};
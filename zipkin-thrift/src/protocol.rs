@@ -0,0 +1,189 @@
+use std::io;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::io::prelude::*;
+
+use thrift::protocol::{TInputProtocol, TOutputProtocol, TBinaryInputProtocol,
+                       TBinaryOutputProtocol, TCompactInputProtocol, TCompactOutputProtocol};
+use thrift::transport::TTransport;
+
+use bytes::Bytes;
+
+/// The thrift protocol to encode or decode the spans
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    /// `TBinaryProtocol`, used by the Zipkin collectors
+    Binary,
+    /// `TCompactProtocol`, smaller and cheaper to encode the integers
+    Compact,
+}
+
+impl Default for Protocol {
+    fn default() -> Self {
+        Protocol::Binary
+    }
+}
+
+impl Protocol {
+    pub fn output_protocol(&self,
+                           transport: Rc<RefCell<Box<TTransport>>>)
+                           -> Box<TOutputProtocol> {
+        match *self {
+            Protocol::Binary => Box::new(TBinaryOutputProtocol::new(transport, true)),
+            Protocol::Compact => Box::new(TCompactOutputProtocol::new(transport)),
+        }
+    }
+
+    pub fn input_protocol(&self,
+                          transport: Rc<RefCell<Box<TTransport>>>)
+                          -> Box<TInputProtocol> {
+        match *self {
+            Protocol::Binary => Box::new(TBinaryInputProtocol::new(transport, true)),
+            Protocol::Compact => Box::new(TCompactInputProtocol::new(transport)),
+        }
+    }
+}
+
+/// The growable buffer shared between the protocol and its owner,
+/// so the allocation is reused across the encodings.
+#[derive(Clone, Debug, Default)]
+pub struct SharedBuffer(Rc<RefCell<io::Cursor<Vec<u8>>>>);
+
+impl SharedBuffer {
+    pub fn new() -> Self {
+        SharedBuffer::default()
+    }
+
+    /// The transport which reads from or writes to the buffer
+    pub fn transport(&self) -> Rc<RefCell<Box<TTransport>>> {
+        Rc::new(RefCell::new(Box::new(self.clone())))
+    }
+
+    /// Take the written bytes, and reset the buffer for the next encoding
+    pub fn drain<F, R>(&self, f: F) -> R
+        where F: FnOnce(&[u8]) -> R
+    {
+        let mut cursor = self.0.borrow_mut();
+        let result = f(cursor.get_ref());
+
+        cursor.get_mut().clear();
+        cursor.set_position(0);
+
+        result
+    }
+
+    /// Take back the allocation, once the transports were dropped
+    pub fn into_inner(self) -> Vec<u8> {
+        Rc::try_unwrap(self.0)
+            .map(|cursor| cursor.into_inner().into_inner())
+            .unwrap_or_default()
+    }
+}
+
+impl From<Vec<u8>> for SharedBuffer {
+    fn from(buf: Vec<u8>) -> Self {
+        SharedBuffer(Rc::new(RefCell::new(io::Cursor::new(buf))))
+    }
+}
+
+impl Read for SharedBuffer {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.borrow_mut().read(buf)
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The bytes to decode, shared between the protocol and its owner to track the consumed bytes.
+#[derive(Clone, Debug)]
+pub struct ReadBuffer(Rc<RefCell<io::Cursor<Bytes>>>);
+
+impl ReadBuffer {
+    pub fn new(bytes: Bytes) -> Self {
+        ReadBuffer(Rc::new(RefCell::new(io::Cursor::new(bytes))))
+    }
+
+    /// The transport which reads from the bytes
+    pub fn transport(&self) -> Rc<RefCell<Box<TTransport>>> {
+        Rc::new(RefCell::new(Box::new(self.clone())))
+    }
+
+    /// Take back the bytes with the number of the consumed bytes, once the transports were dropped
+    pub fn into_inner(self) -> (Bytes, usize) {
+        match Rc::try_unwrap(self.0) {
+            Ok(cursor) => {
+                let cursor = cursor.into_inner();
+                let pos = cursor.position() as usize;
+
+                (cursor.into_inner(), pos)
+            }
+            Err(shared) => {
+                let cursor = shared.borrow();
+
+                (cursor.get_ref().clone(), cursor.position() as usize)
+            }
+        }
+    }
+}
+
+impl Read for ReadBuffer {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.borrow_mut().read(buf)
+    }
+}
+
+impl Write for ReadBuffer {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::PermissionDenied, "read only buffer"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_buffer() {
+        let buf = SharedBuffer::new();
+        let mut writer = buf.clone();
+
+        writer.write_all(b"hello").unwrap();
+
+        assert_eq!(buf.drain(|b| b.to_vec()), b"hello");
+        assert_eq!(buf.drain(|b| b.len()), 0);
+
+        drop(writer);
+
+        assert!(buf.into_inner().capacity() >= 5);
+    }
+
+    #[test]
+    fn read_buffer() {
+        let buf = ReadBuffer::new(Bytes::from(&b"world"[..]));
+        let mut reader = buf.clone();
+        let mut s = [0; 3];
+
+        reader.read_exact(&mut s).unwrap();
+
+        assert_eq!(&s, b"wor");
+        assert!(reader.write_all(b"!").is_err());
+
+        drop(reader);
+
+        let (bytes, pos) = buf.into_inner();
+
+        assert_eq!(&bytes[pos..], b"ld");
+    }
+}
@@ -0,0 +1,169 @@
+//! Check the thrift codec with the span corpus and the golden payloads in `testdata`.
+//!
+//! The thrift goldens were written by hand rather than captured from Zipkin,
+//! see `testdata/corpus.rs` for how to regenerate them with the Java encoder.
+
+extern crate byteorder;
+extern crate serde_json;
+extern crate zipkin_core;
+extern crate zipkin_thrift;
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::PathBuf;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use byteorder::{BigEndian, ByteOrder};
+
+use serde_json::Value;
+
+use zipkin_core::{BytesMut, Encoder};
+use zipkin_thrift::{ToThrift, ThriftCodec, to_vec, from_slice};
+use zipkin_thrift::core::{self, AnnotationType};
+use zipkin_thrift::errors::Error;
+
+include!("../../testdata/corpus.rs");
+
+fn golden(path: &str) -> Vec<u8> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../testdata").join(path);
+    let mut buf = vec![];
+
+    File::open(&path)
+        .and_then(|mut f| f.read_to_end(&mut buf))
+        .unwrap_or_else(|err| panic!("fail to read golden file {:?}, {}", path, err));
+
+    buf
+}
+
+#[test]
+fn thrift_golden() {
+    for (name, span) in corpus::spans() {
+        let expected = golden(&format!("thrift/{}.thrift", name));
+
+        assert_eq!(to_vec(&span).unwrap(), expected, "{}", name);
+
+        let decoded: core::Span = from_slice(&expected).unwrap();
+
+        assert_eq!(decoded, span.to_thrift(), "{}", name);
+
+        // the v1 JSON golden describes the same span, so the units and byte orders must agree
+        let json: Value = serde_json::from_slice(&golden(&format!("v1/{}.json", name))).unwrap();
+
+        check_span(&decoded, &json);
+    }
+}
+
+#[test]
+fn list_of_spans() {
+    let spans = corpus::spans();
+    let mut expected = vec![0x0c, 0, 0, 0, spans.len() as u8];
+
+    for &(name, _) in &spans {
+        expected.extend(golden(&format!("thrift/{}.thrift", name)));
+    }
+
+    let mut codec = ThriftCodec::<_, Error>::new();
+    let mut buf = BytesMut::with_capacity(4096);
+
+    codec
+        .encode(spans.into_iter().map(|(_, span)| span).collect::<Vec<_>>(),
+                &mut buf)
+        .unwrap();
+
+    assert_eq!(&buf[..], &expected[..]);
+}
+
+fn hex(id: i64) -> String {
+    format!("{:016x}", id as u64)
+}
+
+fn check_span(span: &core::Span, json: &Value) {
+    let trace_id = match span.trace_id_high {
+        Some(hi) => hex(hi) + &hex(span.trace_id.unwrap()),
+        None => hex(span.trace_id.unwrap()),
+    };
+
+    assert_eq!(Some(trace_id.as_str()), json["traceId"].as_str());
+    assert_eq!(span.id.map(hex).as_ref().map(|s| s.as_str()),
+               json["id"].as_str());
+    assert_eq!(span.parent_id.map(hex).as_ref().map(|s| s.as_str()),
+               json.get("parentId").and_then(|id| id.as_str()));
+    assert_eq!(span.name.as_ref().map(|s| s.as_str()), json["name"].as_str());
+    assert_eq!(span.timestamp, json["timestamp"].as_i64());
+    assert_eq!(span.duration, json.get("duration").and_then(|d| d.as_i64()));
+    assert_eq!(span.debug, json.get("debug").and_then(|d| d.as_bool()));
+
+    let annotations = span.annotations.as_ref().unwrap();
+    let expected = json.get("annotations").map_or(&[][..], |v| v.as_array().unwrap());
+
+    assert_eq!(annotations.len(), expected.len());
+
+    for (annotation, json) in annotations.iter().zip(expected) {
+        assert_eq!(annotation.timestamp, json["timestamp"].as_i64());
+        assert_eq!(annotation.value.as_ref().map(|s| s.as_str()),
+                   json["value"].as_str());
+        check_endpoint(annotation.host.as_ref(), json.get("endpoint"));
+    }
+
+    let annotations = span.binary_annotations.as_ref().unwrap();
+    let expected = json.get("binaryAnnotations").map_or(&[][..], |v| v.as_array().unwrap());
+
+    assert_eq!(annotations.len(), expected.len());
+
+    for (annotation, json) in annotations.iter().zip(expected) {
+        let value = annotation.value.as_ref().unwrap();
+
+        assert_eq!(annotation.key.as_ref().map(|s| s.as_str()), json["key"].as_str());
+
+        match annotation.annotation_type.unwrap() {
+            AnnotationType::BOOL => {
+                assert_eq!(Some(value[0] == 1), json["value"].as_bool());
+            }
+            AnnotationType::BYTES => assert_eq!(json["type"], "BYTES"),
+            AnnotationType::I16 => {
+                assert_eq!(Some(BigEndian::read_i16(value) as i64),
+                           json["value"].as_i64())
+            }
+            AnnotationType::I32 => {
+                assert_eq!(Some(BigEndian::read_i32(value) as i64),
+                           json["value"].as_i64())
+            }
+            AnnotationType::I64 => {
+                assert_eq!(Some(BigEndian::read_i64(value)), json["value"].as_i64())
+            }
+            AnnotationType::DOUBLE => {
+                assert_eq!(Some(BigEndian::read_f64(value)), json["value"].as_f64())
+            }
+            AnnotationType::STRING => {
+                assert_eq!(Some(String::from_utf8_lossy(value).as_ref()),
+                           json["value"].as_str())
+            }
+        }
+        check_endpoint(annotation.host.as_ref(), json.get("endpoint"));
+    }
+}
+
+fn check_endpoint(host: Option<&core::Endpoint>, json: Option<&Value>) {
+    let (host, json) = match (host, json) {
+        (Some(host), Some(json)) => (host, json),
+        (None, None) => return,
+        (host, json) => panic!("mismatched endpoint, {:?} vs {:?}", host, json),
+    };
+
+    assert_eq!(host.service_name.as_ref().map(|s| s.as_str()),
+               Some(json.get("serviceName").and_then(|s| s.as_str()).unwrap_or("")));
+    assert_eq!(host.ipv4.map(|ip| Ipv4Addr::from(ip as u32).to_string()),
+               Some(json.get("ipv4").and_then(|s| s.as_str()).unwrap_or("0.0.0.0").to_owned()));
+    assert_eq!(host.ipv6
+                   .as_ref()
+                   .map(|ip| {
+                            let mut octets = [0; 16];
+
+                            octets.copy_from_slice(ip);
+
+                            Ipv6Addr::from(octets).to_string()
+                        }),
+               json.get("ipv6").and_then(|s| s.as_str()).map(|s| s.to_owned()));
+    assert_eq!(host.port.map(|port| port as u16 as u64),
+               Some(json.get("port").and_then(|port| port.as_u64()).unwrap_or(0)));
+}
@@ -23,6 +23,7 @@ use std::io::prelude::*;
 use std::net::{TcpStream, Shutdown, ToSocketAddrs};
 use std::sync::Arc;
 use std::thread;
+use std::time::Duration;
 use std::marker::PhantomData;
 
 use clap::{Arg, App};
@@ -91,10 +92,14 @@ impl<'a, S, C> Handler for SimpleProxy<S, C>
                req.uri);
         debug!("received headers:\n{}", req.headers);
 
-        let mut span = self.tracer.span("request");
+        let mut span = self.tracer
+            .start(zipkin::Span::new("request").with_kind(zipkin::Kind::Server));
 
-        annotate!(span, zipkin::SERVER_RECV);
-        annotate!(span, zipkin::CLIENT_ADDR, req.remote_addr.to_string());
+        span.set_remote_endpoint(zipkin::Kind::Server,
+                                 zipkin::Endpoint {
+                                     name: None,
+                                     addr: Some(req.remote_addr),
+                                 });
         annotate!(span, zipkin::HTTP_METHOD, req.method.to_string());
         annotate!(span, zipkin::HTTP_URL, req.uri.to_string());
 
@@ -146,8 +151,6 @@ impl<'a, S, C> SimpleProxy<S, C>
         serde_json::to_writer_pretty(&mut stream, &out)?;
         stream.end()?;
 
-        annotate!(span, zipkin::SERVER_SEND);
-
         self.tracer.submit(span)?;
 
         Ok(())
@@ -200,9 +203,10 @@ impl<'a, S, C> SimpleProxy<S, C>
             .headers(headers)
             .body(Body::BufBody(&buf, buf.len()));
 
-        let mut upstream_span = span.child("request-proxy");
+        let mut upstream_span = self.tracer
+            .child(&span, "request-proxy")
+            .with_kind(zipkin::Kind::Client);
 
-        annotate!(upstream_span, zipkin::CLIENT_SEND);
         annotate!(upstream_span, zipkin::HTTP_METHOD, req.method.to_string());
         annotate!(upstream_span, zipkin::HTTP_URL, req.uri.to_string());
         annotate!(upstream_span, zipkin::HTTP_REQUEST_SIZE, buf.len());
@@ -215,7 +219,6 @@ impl<'a, S, C> SimpleProxy<S, C>
 
         annotate!(upstream_span, zipkin::HTTP_STATUS_CODE, cres.status.to_u16());
         annotate!(upstream_span, zipkin::HTTP_RESPONSE_SIZE, buf.len());
-        annotate!(upstream_span, zipkin::CLIENT_RECV);
 
         self.tracer.submit(upstream_span)?;
 
@@ -239,8 +242,6 @@ impl<'a, S, C> SimpleProxy<S, C>
             _ => res.send(&buf)?,
         }
 
-        annotate!(span, zipkin::SERVER_SEND);
-
         self.tracer.submit(span)?;
 
         Ok(())
@@ -431,7 +432,9 @@ struct Config {
     addr: String,
     threads: Option<usize>,
     sample_rate: usize,
+    sample_config: Option<String>,
     format: String,
+    compression: Option<String>,
     collector_uri: Option<Url>,
 }
 
@@ -464,13 +467,28 @@ fn parse_cmd_line() -> Result<Config> {
                  .takes_value(true)
                  .default_value(&default_sample_rate)
                  .help("Sample rate for span tracing"))
+        .arg(Arg::with_name("sample-config")
+                 .long("sample-config")
+                 .value_name("FILE")
+                 .takes_value(true)
+                 .conflicts_with("sample-rate")
+                 .help("Load sampling configuration in JSON, and reload it when changed"))
         .arg(Arg::with_name("format")
                  .short("f")
                  .long("format")
                  .value_name("FMT")
                  .takes_value(true)
                  .default_value(&default_format)
-                 .help("encode span in format (json, pretty_json, thrift)"))
+                 .help("encode span in format \
+                        (json, pretty_json, json_v2, ndjson, ndjson_v2, thrift, thrift_compact)"))
+        .arg(Arg::with_name("compression")
+                 .short("z")
+                 .long("compression")
+                 .value_name("ENCODING")
+                 .takes_value(true)
+                 .possible_values(&["gzip", "deflate"])
+                 .requires("collector-uri")
+                 .help("Compress the encoded spans sent to the HTTP collector"))
         .arg(Arg::with_name("collector-uri")
                  .short("u")
                  .long("collector-uri")
@@ -487,32 +505,79 @@ fn parse_cmd_line() -> Result<Config> {
                if threads > 1 { Some(threads) } else { None }
            },
            sample_rate: opts.value_of("sample-rate").unwrap().parse()?,
+           sample_config: opts.value_of("sample-config").map(|path| path.to_owned()),
            format: opts.value_of("format").unwrap().to_owned(),
+           compression: opts.value_of("compression").map(|encoding| encoding.to_owned()),
            collector_uri: opts.value_of("collector-uri")
                .and_then(|uri| Url::parse(uri).ok()),
        })
 }
 
+fn compress<'a, C>(codec: C, compression: Option<&str>) -> zipkin::BoxCodec<'a>
+    where C: 'static + zipkin::Codec<'a>
+{
+    match compression {
+        Some("gzip") => {
+            info!("compress with gzip");
+
+            Box::new(zipkin::codec::gzip(codec))
+        }
+        Some("deflate") => {
+            info!("compress with deflate");
+
+            Box::new(zipkin::codec::deflate(codec))
+        }
+        Some(encoding) => panic!("unknown compression: {}", encoding),
+        None => Box::new(codec),
+    }
+}
+
 fn main() {
     pretty_env_logger::init().unwrap();
 
     let cfg = parse_cmd_line().unwrap();
 
+    // the HTTP collector tells Zipkin the content coding, the Kafka messages can't
+    let compression = match (cfg.compression.as_ref(), cfg.collector_uri.as_ref()) {
+        (Some(encoding), Some(url)) if url.scheme() == "http" => Some(encoding.as_str()),
+        (Some(_), _) => panic!("only the http collector supports compression"),
+        (None, _) => None,
+    };
     let codec: zipkin::BoxCodec = match cfg.format.as_str() {
         "json" => {
             info!("use JSON encoder");
 
-            Box::new(zipkin::codec::json())
+            compress(zipkin::codec::json(), compression)
         }
         "pretty" | "pretty_json" => {
             info!("use pretty JSON encoder");
 
-            Box::new(zipkin::codec::pretty_json())
+            compress(zipkin::codec::pretty_json(), compression)
+        }
+        "json_v2" => {
+            info!("use JSON v2 encoder");
+
+            compress(zipkin::codec::json_v2(), compression)
+        }
+        "ndjson" => {
+            info!("use newline delimited JSON encoder");
+
+            compress(zipkin::codec::ndjson(), compression)
+        }
+        "ndjson_v2" => {
+            info!("use newline delimited JSON v2 encoder");
+
+            compress(zipkin::codec::ndjson_v2(), compression)
         }
         "thrift" => {
             info!("use thrift encoder");
 
-            Box::new(zipkin::codec::thrift())
+            compress(zipkin::codec::thrift(), compression)
+        }
+        "thrift_compact" => {
+            info!("use thrift compact encoder");
+
+            compress(zipkin::codec::thrift_compact(), compression)
         }
         _ => panic!("unknown message format: {}", cfg.format),
     };
@@ -534,7 +599,7 @@ fn main() {
                 Box::new(zipkin::collector::new(codec, Box::new(transport)))
             }
             "http" => {
-                let config = zipkin::http::Config::new(codec.mime_type());
+                let config = zipkin::http::Config::from_codec(&*codec);
                 let transport = zipkin::http::Transport::new(url.as_str(), config).unwrap();
 
                 Box::new(zipkin::collector::new(codec, Box::new(transport)))
@@ -545,8 +610,17 @@ fn main() {
         Box::new(DummyCollector::default())
     };
 
-    let sampler = zipkin::FixedRate::new(cfg.sample_rate);
-    let tracer = Arc::new(zipkin::Tracer::with_sampler(sampler, collector));
+    let sampler: zipkin::core::BoxSampler<zipkin::Span> = if let Some(path) = cfg.sample_config {
+        let sampler = zipkin::Reloadable::open(path, zipkin::json::sampler_from_json).unwrap();
+
+        sampler.watch(Duration::from_secs(5));
+
+        Box::new(sampler)
+    } else {
+        Box::new(zipkin::FixedRate::new(cfg.sample_rate))
+    };
+    let tracer = Arc::new(zipkin::Tracer::with_sampler(sampler, collector)
+                              .with_service_name("simple_proxy"));
 
     let server = Server::http(&cfg.addr).unwrap();
 
@@ -15,7 +15,12 @@ pub mod core {
 
 pub use core::constants::*;
 pub use core::{TraceId, SpanId, Timestamp, Endpoint, Annotation, Value, BinaryAnnotation,
-               Annotatable, Span, FixedRate, RateLimit, Tracer, MimeType};
+               Annotatable, Kind, SpanContext, Span, Decision, FixedRate, RateLimit, Reloadable,
+               SpanProcessor, DefaultTags, Redaction, Limits, Clock, AnchoredClock, ManualClock,
+               IdGenerator, SeededIdGenerator, TraceId64, EpochTraceId, AmznTraceId, Tracer,
+               ApiVersion, MimeType, ContentEncoding};
+#[cfg(any(feature = "compress", feature = "doc"))]
+pub use core::{Compressed, Compression};
 
 pub trait Codec<'a>: core::Codec<Item = Vec<Span<'a>>, Error = Error> + MimeType {}
 
@@ -79,7 +84,7 @@ extern crate zipkin_async;
 #[cfg(any(feature = "async", feature = "doc"))]
 pub mod async {
     pub use zipkin_async::errors::{Error, ErrorKind, Result};
-    pub use zipkin_async::{AsyncCollector, BaseAsyncCollector};
+    pub use zipkin_async::{AsyncCollector, BaseAsyncCollector, PipelinedCollector, AsyncTransport};
 }
 
 #[cfg(any(feature = "json", feature = "doc"))]
@@ -88,7 +93,8 @@ extern crate zipkin_json;
 pub mod json {
     pub use zipkin_json::errors::{Error, ErrorKind, Result};
     pub use zipkin_json::{to_json, to_string, to_string_pretty, to_vec, to_vec_pretty, to_writer,
-                          to_writer_pretty, JsonCodec as Codec};
+                          to_writer_pretty, sampler_from_json, JsonCodec as Codec,
+                          NdJsonCodec};
 }
 
 #[cfg(any(feature = "thrift", feature = "doc"))]
@@ -96,11 +102,26 @@ extern crate zipkin_thrift;
 #[cfg(any(feature = "thrift", feature = "doc"))]
 pub mod thrift {
     pub use zipkin_thrift::errors::{Error, ErrorKind, Result};
-    pub use zipkin_thrift::{to_thrift, to_vec, to_writer, ThriftCodec as Codec};
+    pub use zipkin_thrift::{to_thrift, to_vec, to_writer, from_slice, Protocol,
+                            ThriftCodec as Codec, ThriftDecoder as Decoder};
 }
 
 pub mod codec {
     use super::{Span, Error};
+    #[cfg(any(feature = "compress", feature = "doc"))]
+    use super::Compressed;
+
+    /// Compress the messages of the codec with gzip
+    #[cfg(any(feature = "compress", feature = "doc"))]
+    pub fn gzip<C>(codec: C) -> Compressed<C> {
+        Compressed::gzip(codec)
+    }
+
+    /// Compress the messages of the codec with deflate
+    #[cfg(any(feature = "compress", feature = "doc"))]
+    pub fn deflate<C>(codec: C) -> Compressed<C> {
+        Compressed::deflate(codec)
+    }
 
     #[cfg(any(feature = "json", feature = "doc"))]
     pub fn json<'a>() -> super::json::Codec<Vec<Span<'a>>, Error> {
@@ -112,10 +133,30 @@ pub mod codec {
         super::json::Codec::pretty()
     }
 
+    #[cfg(any(feature = "json", feature = "doc"))]
+    pub fn json_v2<'a>() -> super::json::Codec<Vec<Span<'a>>, Error> {
+        super::json::Codec::new().with_version(super::ApiVersion::V2)
+    }
+
+    #[cfg(any(feature = "json", feature = "doc"))]
+    pub fn ndjson<'a>() -> super::json::NdJsonCodec<Span<'a>, Error> {
+        super::json::NdJsonCodec::new()
+    }
+
+    #[cfg(any(feature = "json", feature = "doc"))]
+    pub fn ndjson_v2<'a>() -> super::json::NdJsonCodec<Span<'a>, Error> {
+        super::json::NdJsonCodec::new().with_version(super::ApiVersion::V2)
+    }
+
     #[cfg(any(feature = "thrift", feature = "doc"))]
     pub fn thrift<'a>() -> super::thrift::Codec<Vec<Span<'a>>, Error> {
         super::thrift::Codec::new()
     }
+
+    #[cfg(any(feature = "thrift", feature = "doc"))]
+    pub fn thrift_compact<'a>() -> super::thrift::Codec<Vec<Span<'a>>, Error> {
+        super::thrift::Codec::compact()
+    }
 }
 
 #[cfg(any(feature = "kafka", feature = "doc"))]
@@ -131,5 +172,6 @@ extern crate zipkin_http;
 #[cfg(any(feature = "http", feature = "doc"))]
 pub mod http {
     pub use zipkin_http::errors::{Error, ErrorKind, Result};
-    pub use zipkin_http::{HttpConfig as Config, HttpTransport as Transport};
+    pub use zipkin_http::{HttpConfig as Config, HttpTransport as Transport,
+                          AsyncHttpTransport as AsyncTransport};
 }